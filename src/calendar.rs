@@ -0,0 +1,338 @@
+use crate::constants::{Months, Weekdays};
+
+const WEEKDAYS: [Weekdays; 7] = [
+    Weekdays::Sun,
+    Weekdays::Mon,
+    Weekdays::Tue,
+    Weekdays::Wed,
+    Weekdays::Thu,
+    Weekdays::Fri,
+    Weekdays::Sat,
+];
+
+const MONTHS: [Months; 12] = [
+    Months::Jan,
+    Months::Feb,
+    Months::Mar,
+    Months::Apr,
+    Months::May,
+    Months::Jun,
+    Months::Jul,
+    Months::Aug,
+    Months::Sep,
+    Months::Oct,
+    Months::Nov,
+    Months::Dec,
+];
+
+// Returns the number of the month, starting with 1 for January.
+pub fn month_number(m: Months) -> u32 {
+    m as u32 + 1
+}
+
+pub fn month_from_number(n: u32) -> Option<Months> {
+    match n {
+        1..=12 => Some(MONTHS[n as usize - 1]),
+        _ => None,
+    }
+}
+
+// Returns the day of the week, starting with 0 for Monday.
+pub fn weekday_from_number(n: u32) -> Weekdays {
+    WEEKDAYS[(n as usize + 1) % 7]
+}
+
+// Returns the English ordinal suffix of a day, e.g. "st" for 1 and 21.
+pub fn ordinal_suffix(day: u32) -> &'static str {
+    match (day % 10, day % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    }
+}
+
+pub fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+pub fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year(year) => 29,
+        _ => 28,
+    }
+}
+
+// Returns the day of the week of a date in the proleptic Gregorian calendar.
+pub fn weekday(year: i32, month: u32, day: u32) -> Weekdays {
+    // Sakamoto's method.
+    let t = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+    let y = if month < 3 { year - 1 } else { year };
+    let w = y + y.div_euclid(4) - y.div_euclid(100)
+        + y.div_euclid(400)
+        + t[(month - 1) as usize]
+        + day as i32;
+    WEEKDAYS[w.rem_euclid(7) as usize]
+}
+
+pub fn days_in_year(year: i32) -> u32 {
+    if is_leap_year(year) {
+        366
+    } else {
+        365
+    }
+}
+
+// Returns the day of the year, starting with 1 for January 1.
+pub fn day_of_year(year: i32, month: u32, day: u32) -> u32 {
+    (1..month).map(|m| days_in_month(year, m)).sum::<u32>() + day
+}
+
+// Returns the month and day of the nth day of the year.
+pub fn date_from_day_of_year(year: i32, n: u32) -> Option<(u32, u32)> {
+    if n == 0 || n > days_in_year(year) {
+        return None;
+    }
+    let mut n = n;
+    for m in 1..=12 {
+        let days = days_in_month(year, m);
+        if n <= days {
+            return Some((m, n));
+        }
+        n -= days;
+    }
+    None
+}
+
+// Returns the quarter of the month, starting with 1 for January to March.
+pub fn quarter(month: u32) -> u32 {
+    (month - 1) / 3 + 1
+}
+
+// Returns the number of ISO 8601 weeks of a week-year. A year has 53 weeks if
+// it starts on a Thursday, or on a Wednesday in leap years.
+pub fn weeks_in_year(year: i32) -> u32 {
+    match weekday(year, 1, 1) {
+        Weekdays::Thu => 53,
+        Weekdays::Wed if is_leap_year(year) => 53,
+        _ => 52,
+    }
+}
+
+// Returns the ISO 8601 week-year and week of a date. Weeks start on Monday and
+// the first week of a year is the one containing January 4.
+pub fn iso_week(year: i32, month: u32, day: u32) -> (i32, u32) {
+    let n = day_of_year(year, month, day) as i32;
+    let w = (n - weekday(year, month, day) as i32 + 9) / 7;
+    if w < 1 {
+        (year - 1, weeks_in_year(year - 1))
+    } else if w as u32 > weeks_in_year(year) {
+        (year + 1, 1)
+    } else {
+        (year, w as u32)
+    }
+}
+
+// Returns the date of a weekday in an ISO 8601 week.
+pub fn date_from_iso_week(year: i32, week: u32, w: Weekdays) -> Option<(i32, u32, u32)> {
+    if week == 0 || week > weeks_in_year(year) {
+        return None;
+    }
+    // Day of the year, which may lie in the previous or next year.
+    let n = (week as i32 - 1) * 7 + w as i32 - weekday(year, 1, 4) as i32 + 4;
+    let (y, n) = if n < 1 {
+        (year - 1, n + days_in_year(year - 1) as i32)
+    } else if n > days_in_year(year) as i32 {
+        (year + 1, n - days_in_year(year) as i32)
+    } else {
+        (year, n)
+    };
+    date_from_day_of_year(y, n as u32).map(|(m, d)| (y, m, d))
+}
+
+// Returns the number of days since January 1, 1970.
+pub fn days_from_date(year: i32, month: u32, day: u32) -> i64 {
+    // Howard Hinnant's days_from_civil, with years starting in March.
+    let y = if month <= 2 { year - 1 } else { year } as i64;
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let m = month as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+// Returns the year, month and day of a number of days since January 1, 1970.
+pub fn date_from_days(days: i64) -> (i32, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    (y as i32, m, d)
+}
+
+// Offset of local time from UTC in seconds, e.g. 7200 for +02:00.
+#[derive(PartialEq, Copy, Clone, Debug, Default)]
+pub struct UtcOffset(pub i32);
+
+impl std::str::FromStr for UtcOffset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("utc") || s == "Z" {
+            return Ok(UtcOffset(0));
+        }
+        let err = || format!("invalid offset {}, expected utc or +HH:MM", s);
+        let sign = match s.chars().next() {
+            Some('+') => 1,
+            Some('-') => -1,
+            _ => return Err(err()),
+        };
+        let (h, m) = s[1..].split_once(':').ok_or_else(err)?;
+        if h.len() != 2 || m.len() != 2 {
+            return Err(err());
+        }
+        match (h.parse::<i32>(), m.parse::<i32>()) {
+            (Ok(h), Ok(m)) if h <= 23 && m <= 59 => Ok(UtcOffset(sign * (h * 3600 + m * 60))),
+            _ => Err(err()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calendar::{
+        date_from_day_of_year, date_from_days, date_from_iso_week, day_of_year, days_from_date,
+        days_in_month, is_leap_year, iso_week, month_from_number, ordinal_suffix, quarter, weekday,
+        UtcOffset,
+    };
+    use crate::constants::Months;
+    use crate::constants::Weekdays;
+
+    #[test]
+    fn test_leap_year() {
+        assert!(is_leap_year(2000));
+        assert!(is_leap_year(2020));
+        assert!(!is_leap_year(1900));
+        assert!(!is_leap_year(2019));
+        assert_eq!(days_in_month(2019, 2), 28);
+        assert_eq!(days_in_month(2020, 2), 29);
+        assert_eq!(days_in_month(2021, 4), 30);
+        assert_eq!(days_in_month(2021, 12), 31);
+    }
+
+    #[test]
+    fn test_month_from_number() {
+        assert_eq!(month_from_number(1), Some(Months::Jan));
+        assert_eq!(month_from_number(12), Some(Months::Dec));
+        assert_eq!(month_from_number(0), None);
+        assert_eq!(month_from_number(13), None);
+    }
+
+    #[test]
+    fn test_ordinal_suffix() {
+        let want = [
+            (1, "st"),
+            (2, "nd"),
+            (3, "rd"),
+            (4, "th"),
+            (11, "th"),
+            (12, "th"),
+            (13, "th"),
+            (21, "st"),
+            (22, "nd"),
+            (23, "rd"),
+            (30, "th"),
+            (31, "st"),
+        ];
+        for (day, suffix) in want.iter() {
+            assert_eq!(ordinal_suffix(*day), *suffix);
+        }
+    }
+
+    #[test]
+    fn test_weekday() {
+        assert_eq!(weekday(2003, 2, 1), Weekdays::Sat);
+        assert_eq!(weekday(2021, 6, 7), Weekdays::Mon);
+        assert_eq!(weekday(2000, 2, 29), Weekdays::Tue);
+        assert_eq!(weekday(1970, 1, 1), Weekdays::Thu);
+    }
+
+    #[test]
+    fn test_day_of_year() {
+        assert_eq!(day_of_year(2003, 2, 1), 32);
+        assert_eq!(day_of_year(2021, 6, 1), 152);
+        assert_eq!(day_of_year(2020, 12, 31), 366);
+        assert_eq!(date_from_day_of_year(2021, 152), Some((6, 1)));
+        assert_eq!(date_from_day_of_year(2020, 366), Some((12, 31)));
+        assert_eq!(date_from_day_of_year(2021, 366), None);
+        assert_eq!(date_from_day_of_year(2021, 0), None);
+    }
+
+    #[test]
+    fn test_quarter() {
+        assert_eq!(quarter(1), 1);
+        assert_eq!(quarter(3), 1);
+        assert_eq!(quarter(4), 2);
+        assert_eq!(quarter(12), 4);
+    }
+
+    #[test]
+    fn test_iso_week() {
+        assert_eq!(iso_week(2003, 2, 1), (2003, 5));
+        assert_eq!(iso_week(2021, 6, 1), (2021, 22));
+        assert_eq!(iso_week(2021, 1, 1), (2020, 53));
+        assert_eq!(iso_week(2019, 12, 30), (2020, 1));
+        assert_eq!(iso_week(2026, 12, 31), (2026, 53));
+        assert_eq!(
+            date_from_iso_week(2003, 5, Weekdays::Sat),
+            Some((2003, 2, 1))
+        );
+        assert_eq!(
+            date_from_iso_week(2021, 22, Weekdays::Mon),
+            Some((2021, 5, 31))
+        );
+        assert_eq!(
+            date_from_iso_week(2020, 53, Weekdays::Fri),
+            Some((2021, 1, 1))
+        );
+        assert_eq!(
+            date_from_iso_week(2020, 1, Weekdays::Mon),
+            Some((2019, 12, 30))
+        );
+        assert_eq!(date_from_iso_week(2021, 53, Weekdays::Mon), None);
+    }
+
+    #[test]
+    fn test_days() {
+        assert_eq!(days_from_date(1970, 1, 1), 0);
+        assert_eq!(days_from_date(2003, 2, 1), 12084);
+        assert_eq!(days_from_date(1969, 12, 31), -1);
+        assert_eq!(date_from_days(0), (1970, 1, 1));
+        assert_eq!(date_from_days(12084), (2003, 2, 1));
+        assert_eq!(date_from_days(-1), (1969, 12, 31));
+        assert_eq!(date_from_days(11016), (2000, 2, 29));
+        for n in -1000..1000 {
+            let (y, m, d) = date_from_days(n * 37);
+            assert_eq!(days_from_date(y, m, d), n * 37);
+        }
+    }
+
+    #[test]
+    fn test_utc_offset() {
+        assert_eq!("utc".parse(), Ok(UtcOffset(0)));
+        assert_eq!("+02:00".parse(), Ok(UtcOffset(7200)));
+        assert_eq!("-05:30".parse(), Ok(UtcOffset(-19800)));
+        assert!("02:00".parse::<UtcOffset>().is_err());
+        assert!("+2:00".parse::<UtcOffset>().is_err());
+        assert!("+24:00".parse::<UtcOffset>().is_err());
+    }
+}
@@ -0,0 +1,428 @@
+use crate::error::MyCustomError;
+use crate::{PlanError, Rename, RenamePlan};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Determines what happens if several files would get the same name or a new
+// name is taken by a file which is not renamed.
+#[derive(PartialEq, Copy, Clone, Debug, Default)]
+pub enum ConflictStrategy {
+    // Fails the files of the conflict.
+    #[default]
+    Abort,
+    // Leaves the files of the conflict alone.
+    Skip,
+    // Appends _1, _2, .. to the new name of all files but the first.
+    Suffix,
+    // Renames the most recently modified file and leaves the others alone.
+    Newer,
+    // Renames the largest file and leaves the others alone.
+    Larger,
+    // Renames the files if they have the same contents as each other and as
+    // the existing file, which is overwritten. Fails them otherwise.
+    Identical,
+}
+
+impl std::str::FromStr for ConflictStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "abort" => Ok(ConflictStrategy::Abort),
+            "skip" => Ok(ConflictStrategy::Skip),
+            "suffix" => Ok(ConflictStrategy::Suffix),
+            "newer" => Ok(ConflictStrategy::Newer),
+            "larger" => Ok(ConflictStrategy::Larger),
+            "identical" => Ok(ConflictStrategy::Identical),
+            _ => Err(format!(
+                "unknown conflict strategy {}, expected abort, skip, suffix, newer, larger or identical",
+                s
+            )),
+        }
+    }
+}
+
+// A new name, the renames to it and whether a file which is not renamed
+// already has this name.
+struct Conflict {
+    target: PathBuf,
+    renames: Vec<usize>,
+    exists: bool,
+}
+
+enum Decision {
+    Keep,
+    Retarget(PathBuf),
+    Skip(MyCustomError),
+    Fail(MyCustomError),
+}
+
+// Resolves the conflicts of the plan with the given strategy and orders its
+// renames, so that a file is moved away before another file takes its name.
+// Resolving a conflict can cause another one, e.g. a skipped file keeps a name
+// which another file was going to take, so conflicts are resolved until there
+// are none left.
+pub fn resolve(plan: &mut RenamePlan, strategy: ConflictStrategy) -> Result<(), MyCustomError> {
+    // Names which may be overwritten or shared according to the strategy.
+    let mut accepted = HashSet::new();
+    loop {
+        let conflicts = conflicts(&plan.renames, &accepted);
+        if conflicts.is_empty() {
+            break;
+        }
+        let mut taken: HashSet<PathBuf> = plan
+            .renames
+            .iter()
+            .flat_map(|r| vec![r.from.clone(), r.to.clone()])
+            .collect();
+        let mut decisions: Vec<Decision> = plan.renames.iter().map(|_| Decision::Keep).collect();
+        for c in conflicts {
+            for (i, d) in decide(&c, &plan.renames, strategy, &mut taken)? {
+                if matches!(d, Decision::Keep) && strategy != ConflictStrategy::Suffix {
+                    // The strategy chose to overwrite or share the name.
+                    accepted.insert(c.target.clone());
+                }
+                decisions[i] = d;
+            }
+        }
+        let renames = std::mem::take(&mut plan.renames);
+        for (mut r, d) in renames.into_iter().zip(decisions) {
+            match d {
+                Decision::Keep => plan.renames.push(r),
+                Decision::Retarget(to) => {
+                    r.to = to;
+                    plan.renames.push(r);
+                }
+                Decision::Skip(error) => plan.skipped.push(PlanError {
+                    path: r.from,
+                    error,
+                }),
+                Decision::Fail(error) => plan.errors.push(PlanError {
+                    path: r.from,
+                    error,
+                }),
+            }
+        }
+    }
+    for r in &mut plan.renames {
+        r.overwrite = accepted.contains(&r.to);
+    }
+    order(plan);
+    Ok(())
+}
+
+// Returns the names which more than one file would get or which are taken by
+// a file which is not renamed.
+fn conflicts(renames: &[Rename], accepted: &HashSet<PathBuf>) -> Vec<Conflict> {
+    let vacated: HashSet<&Path> = renames
+        .iter()
+        .filter(|r| r.from != r.to)
+        .map(|r| r.from.as_path())
+        .collect();
+    let mut groups: BTreeMap<&Path, Vec<usize>> = BTreeMap::new();
+    for (i, r) in renames.iter().enumerate() {
+        if r.from != r.to {
+            groups.entry(&r.to).or_default().push(i);
+        }
+    }
+    groups
+        .into_iter()
+        .filter(|(target, _)| !accepted.contains(*target))
+        .map(|(target, renames)| Conflict {
+            target: target.to_path_buf(),
+            exists: target.symlink_metadata().is_ok() && !vacated.contains(target),
+            renames,
+        })
+        .filter(|c| c.renames.len() > 1 || c.exists)
+        .collect()
+}
+
+fn decide(
+    c: &Conflict,
+    renames: &[Rename],
+    strategy: ConflictStrategy,
+    taken: &mut HashSet<PathBuf>,
+) -> Result<Vec<(usize, Decision)>, MyCustomError> {
+    let skip = || Decision::Skip(collision(c, renames));
+    let fail = || Decision::Fail(collision(c, renames));
+    let all = |d: &dyn Fn() -> Decision| c.renames.iter().map(|i| (*i, d())).collect();
+    let decisions = match strategy {
+        ConflictStrategy::Abort => all(&fail),
+        ConflictStrategy::Skip => all(&skip),
+        ConflictStrategy::Suffix => c
+            .renames
+            .iter()
+            .enumerate()
+            .map(|(n, i)| match n {
+                0 if !c.exists => (*i, Decision::Keep),
+                _ => (*i, Decision::Retarget(free_name(&c.target, taken))),
+            })
+            .collect(),
+        ConflictStrategy::Newer | ConflictStrategy::Larger => {
+            let key = |p: &Path| -> Result<(u64, std::time::SystemTime), MyCustomError> {
+                let m = fs::metadata(p)?;
+                Ok(match strategy {
+                    ConflictStrategy::Newer => (0, m.modified()?),
+                    _ => (m.len(), std::time::UNIX_EPOCH),
+                })
+            };
+            // The existing file wins a tie, so that nothing is overwritten.
+            let mut winner = None;
+            let mut best = match c.exists {
+                true => Some(key(&c.target)?),
+                false => None,
+            };
+            for i in &c.renames {
+                let k = key(&renames[*i].from)?;
+                if best.is_none_or(|b| k > b) {
+                    best = Some(k);
+                    winner = Some(*i);
+                }
+            }
+            c.renames
+                .iter()
+                .map(|i| match winner == Some(*i) {
+                    true => (*i, Decision::Keep),
+                    false => (*i, skip()),
+                })
+                .collect()
+        }
+        ConflictStrategy::Identical => {
+            let mut paths: Vec<&Path> = c
+                .renames
+                .iter()
+                .map(|i| renames[*i].from.as_path())
+                .collect();
+            if c.exists {
+                paths.push(&c.target);
+            }
+            let first = fs::read(paths[0])?;
+            let mut identical = true;
+            for p in &paths[1..] {
+                if fs::read(p)? != first {
+                    identical = false;
+                    break;
+                }
+            }
+            match identical {
+                true => all(&|| Decision::Keep),
+                false => all(&fail),
+            }
+        }
+    };
+    Ok(decisions)
+}
+
+fn collision(c: &Conflict, renames: &[Rename]) -> MyCustomError {
+    MyCustomError::CollisionError(
+        c.target.display().to_string(),
+        c.renames
+            .iter()
+            .map(|i| renames[*i].from.display().to_string())
+            .collect(),
+        c.exists,
+    )
+}
+
+// Returns the first name of the form stem_n.ext which is neither taken by the
+// plan nor by an existing file and marks it as taken.
+fn free_name(target: &Path, taken: &mut HashSet<PathBuf>) -> PathBuf {
+    let stem = target.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = match target.extension() {
+        Some(x) => format!(".{}", x.to_string_lossy()),
+        None => String::new(),
+    };
+    let mut n = 1;
+    loop {
+        let p = target.with_file_name(format!("{}_{}{}", stem, n, ext));
+        if !taken.contains(&p) && p.symlink_metadata().is_err() {
+            taken.insert(p.clone());
+            return p;
+        }
+        n += 1;
+    }
+}
+
+// Orders the renames, so that no file is renamed to the name of a file which is
+// renamed later. Renames which wait for each other in a cycle fail.
+fn order(plan: &mut RenamePlan) {
+    let mut pending = std::mem::take(&mut plan.renames);
+    while !pending.is_empty() {
+        let froms: HashSet<PathBuf> = pending
+            .iter()
+            .filter(|r| r.from != r.to)
+            .map(|r| r.from.clone())
+            .collect();
+        let (ready, blocked): (Vec<_>, Vec<_>) = pending
+            .into_iter()
+            .partition(|r| r.from == r.to || !froms.contains(&r.to));
+        if ready.is_empty() {
+            for r in blocked {
+                plan.errors.push(PlanError {
+                    error: MyCustomError::Default(format!(
+                        "{} cannot be renamed to {}, the renames form a cycle",
+                        r.from.display(),
+                        r.to.display()
+                    )),
+                    path: r.from,
+                });
+            }
+            break;
+        }
+        plan.renames.extend(ready);
+        pending = blocked;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::conflict::{resolve, ConflictStrategy};
+    use crate::{MyCustomError, Rename, RenamePlan};
+    use std::path::PathBuf;
+
+    // Creates the files in a new directory and plans the renames between them.
+    fn setup(
+        test: &str,
+        files: &[(&str, &str)],
+        renames: &[(&str, &str)],
+    ) -> (PathBuf, RenamePlan) {
+        let dir = std::env::temp_dir().join(format!("tidy_test_conflict_{}", test));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, contents) in files {
+            std::fs::write(dir.join(name), contents).unwrap();
+        }
+        let plan = RenamePlan {
+            renames: renames
+                .iter()
+                .map(|(from, to)| Rename {
+                    from: dir.join(from),
+                    to: dir.join(to),
+                    interpretation: String::new(),
+                    overwrite: false,
+                })
+                .collect(),
+            ..Default::default()
+        };
+        (dir, plan)
+    }
+
+    fn names(
+        dir: &PathBuf,
+        plan: &RenamePlan,
+    ) -> (Vec<(String, String)>, Vec<String>, Vec<String>) {
+        let name = |p: &PathBuf| p.strip_prefix(dir).unwrap().display().to_string();
+        (
+            plan.renames
+                .iter()
+                .map(|r| (name(&r.from), name(&r.to)))
+                .collect(),
+            plan.errors.iter().map(|e| name(&e.path)).collect(),
+            plan.skipped.iter().map(|e| name(&e.path)).collect(),
+        )
+    }
+
+    type Want<'a> = (Vec<(&'a str, &'a str)>, Vec<&'a str>, Vec<&'a str>);
+
+    fn check(
+        test: &str,
+        strategy: ConflictStrategy,
+        files: &[(&str, &str)],
+        renames: &[(&str, &str)],
+        want: Want,
+    ) -> Result<(), MyCustomError> {
+        let (dir, mut plan) = setup(test, files, renames);
+        resolve(&mut plan, strategy)?;
+        let (got_renames, got_errors, got_skipped) = names(&dir, &plan);
+        let (want_renames, want_errors, want_skipped) = want;
+        let to_strings = |v: Vec<&str>| v.into_iter().map(String::from).collect::<Vec<_>>();
+        assert_eq!(
+            got_renames,
+            want_renames
+                .into_iter()
+                .map(|(a, b)| (a.to_string(), b.to_string()))
+                .collect::<Vec<_>>(),
+            "{}",
+            test
+        );
+        assert_eq!(got_errors, to_strings(want_errors), "{}", test);
+        assert_eq!(got_skipped, to_strings(want_skipped), "{}", test);
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_strategies() -> Result<(), MyCustomError> {
+        let files = [("a", "1"), ("b", "22"), ("c", "333"), ("t.txt", "22")];
+        check(
+            "abort",
+            ConflictStrategy::Abort,
+            &files,
+            &[("a", "x.txt"), ("b", "x.txt"), ("c", "y.txt")],
+            (vec![("c", "y.txt")], vec!["a", "b"], vec![]),
+        )?;
+        check(
+            "skip",
+            ConflictStrategy::Skip,
+            &files,
+            &[("a", "t.txt"), ("c", "y.txt")],
+            (vec![("c", "y.txt")], vec![], vec!["a"]),
+        )?;
+        check(
+            "suffix",
+            ConflictStrategy::Suffix,
+            &files,
+            &[("a", "x.txt"), ("b", "x.txt"), ("c", "t.txt")],
+            (
+                vec![("a", "x.txt"), ("b", "x_1.txt"), ("c", "t_1.txt")],
+                vec![],
+                vec![],
+            ),
+        )?;
+        check(
+            "larger",
+            ConflictStrategy::Larger,
+            &files,
+            &[("a", "t.txt"), ("b", "x.txt"), ("c", "x.txt")],
+            (vec![("c", "x.txt")], vec![], vec!["a", "b"]),
+        )?;
+        check(
+            "identical",
+            ConflictStrategy::Identical,
+            &files,
+            &[("b", "t.txt"), ("a", "x.txt"), ("c", "x.txt")],
+            (vec![("b", "t.txt")], vec!["a", "c"], vec![]),
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_order() -> Result<(), MyCustomError> {
+        let files = [("a", ""), ("b", ""), ("c", ""), ("d", "")];
+        // b is moved away before a takes its name.
+        check(
+            "chain",
+            ConflictStrategy::Abort,
+            &files,
+            &[("a", "b"), ("b", "e")],
+            (vec![("b", "e"), ("a", "b")], vec![], vec![]),
+        )?;
+        check(
+            "cycle",
+            ConflictStrategy::Abort,
+            &files,
+            &[("a", "b"), ("b", "a"), ("c", "e")],
+            (vec![("c", "e")], vec!["a", "b"], vec![]),
+        )?;
+        // Skipping c keeps its name, which d was going to take.
+        check(
+            "cascade",
+            ConflictStrategy::Skip,
+            &files,
+            &[("c", "a"), ("d", "c")],
+            (vec![], vec![], vec!["c", "d"]),
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use crate::filename_parser::Data;
+use std::collections::HashMap;
+use std::path::Path;
+
+// Determines which files share a sequence of numbers.
+#[derive(PartialEq, Copy, Clone, Debug, Default)]
+pub enum CounterScope {
+    // One sequence for all files.
+    #[default]
+    Global,
+    // One sequence per directory.
+    Directory,
+    // One sequence per date parsed from the filename.
+    Date,
+}
+
+impl std::str::FromStr for CounterScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "global" => Ok(CounterScope::Global),
+            "dir" => Ok(CounterScope::Directory),
+            "date" => Ok(CounterScope::Date),
+            _ => Err(format!(
+                "unknown counter scope {}, expected global, dir or date",
+                s
+            )),
+        }
+    }
+}
+
+pub struct Counter {
+    start: u32,
+    scope: CounterScope,
+    next: HashMap<String, u32>,
+}
+
+impl Counter {
+    pub fn new(start: u32, scope: CounterScope) -> Counter {
+        Counter {
+            start,
+            scope,
+            next: HashMap::new(),
+        }
+    }
+
+    // Returns the next number in the sequence of the file at path.
+    pub fn next(&mut self, path: &Path, d: &Data) -> u32 {
+        let key = match self.scope {
+            CounterScope::Global => String::from(""),
+            CounterScope::Directory => match path.parent() {
+                Some(p) => p.to_string_lossy().to_string(),
+                None => String::from(""),
+            },
+            CounterScope::Date => format!("{:?}-{:?}-{:?}", d.year, d.month, d.day),
+        };
+        let n = self.next.entry(key).or_insert(self.start);
+        *n += 1;
+        *n - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::counter::{Counter, CounterScope};
+    use crate::filename_parser::{FileNameParser, ParseOpt};
+    use crate::format_parser::FormatParser;
+    use crate::MyCustomError;
+    use std::path::Path;
+
+    #[test]
+    fn test_scopes() -> Result<(), MyCustomError> {
+        let mut source = FormatParser::new("20030201_%0");
+        source.parse()?;
+        let opt = ParseOpt::default();
+        let mut a = FileNameParser::new("20210601_a", source.holes, &opt);
+        a.parse(&source.nodes)?;
+        let mut b = FileNameParser::new("20210602_b", source.holes, &opt);
+        b.parse(&source.nodes)?;
+        let x = Path::new("x/20210601_a.pdf");
+        let y = Path::new("y/20210601_a.pdf");
+
+        let mut c = Counter::new(1, CounterScope::Global);
+        assert_eq!(c.next(x, &a.d), 1);
+        assert_eq!(c.next(y, &b.d), 2);
+        assert_eq!(c.next(x, &a.d), 3);
+
+        let mut c = Counter::new(0, CounterScope::Directory);
+        assert_eq!(c.next(x, &a.d), 0);
+        assert_eq!(c.next(y, &a.d), 0);
+        assert_eq!(c.next(x, &b.d), 1);
+
+        let mut c = Counter::new(1, CounterScope::Date);
+        assert_eq!(c.next(x, &a.d), 1);
+        assert_eq!(c.next(x, &b.d), 1);
+        assert_eq!(c.next(y, &a.d), 2);
+        Ok(())
+    }
+}
@@ -7,6 +7,26 @@ pub enum MyCustomError {
     PathError,
     Default(String),
     ParseError(String),
+    // The filename and the reason why its date is invalid.
+    InvalidDateError(String, String),
+    // The identifiers of the target pattern which the source does not provide.
+    MissingIdentifiersError(Vec<String>),
+    // The filename and two different ways in which it matches the source.
+    AmbiguousMatchError(String, String, String),
+    // The filename, the position in characters where it stops matching, what
+    // the pattern expects there and the text found instead.
+    MismatchError(String, usize, String, String),
+    // The pattern, the range of characters the error refers to and what is
+    // wrong with them.
+    PatternError(String, usize, usize, String),
+    // A new name, the files which would get it and whether a file which is
+    // not renamed already has it.
+    CollisionError(String, Vec<String>, bool),
+    // The new and the old name of a file which cannot be renamed back and why.
+    UndoError(String, String, String),
+    // Why a rename of an atomic run failed, the new and old names of the files
+    // which were renamed back and the files which could not be renamed back.
+    RollbackError(String, Vec<(String, String)>, Vec<String>),
 }
 
 impl std::error::Error for MyCustomError {}
@@ -30,7 +50,87 @@ impl fmt::Display for MyCustomError {
             MyCustomError::IoError(x) => write!(f, "IO error :{:?}", x),
             MyCustomError::PathError => write!(f, "path error"),
             MyCustomError::Default(x) => write!(f, "{}", x),
-            MyCustomError::ParseError(x) => write!(f, "{}",x),
+            MyCustomError::ParseError(x) => write!(f, "{}", x),
+            MyCustomError::InvalidDateError(name, reason) => {
+                write!(f, "invalid date in {}: {}", name, reason)
+            }
+            MyCustomError::MissingIdentifiersError(x) => write!(
+                f,
+                "the target uses identifiers which are missing in the source: {}",
+                x.join(", ")
+            ),
+            MyCustomError::AmbiguousMatchError(name, a, b) => write!(
+                f,
+                "{} matches the source pattern in more than one way: {} or {}",
+                name, a, b
+            ),
+            MyCustomError::MismatchError(name, pos, expected, found) => {
+                let found_text = if found.is_empty() {
+                    "the end of the name".to_string()
+                } else {
+                    format!("\"{}\"", found)
+                };
+                write!(
+                    f,
+                    "{} does not match the source pattern: expected {} at character {}, found {}\n{}",
+                    name,
+                    expected,
+                    pos + 1,
+                    found_text,
+                    caret(name, *pos, found.chars().count())
+                )
+            }
+            MyCustomError::PatternError(pattern, start, end, message) => write!(
+                f,
+                "invalid pattern at character {}: {}\n{}",
+                start + 1,
+                message,
+                caret(pattern, *start, end - start)
+            ),
+            MyCustomError::CollisionError(target, sources, true) => write!(
+                f,
+                "{} would be renamed to {}, which already exists",
+                sources.join(", "),
+                target
+            ),
+            MyCustomError::CollisionError(target, sources, false) => write!(
+                f,
+                "{} would all be renamed to {}",
+                sources.join(", "),
+                target
+            ),
+            MyCustomError::UndoError(to, from, reason) => {
+                write!(f, "cannot rename {} back to {}: {}", to, from, reason)
+            }
+            MyCustomError::RollbackError(cause, rolled_back, failed) => {
+                match failed.len() {
+                    0 => write!(f, "{}, all renames were rolled back", cause)?,
+                    n => write!(
+                        f,
+                        "{}, {} of {} renames could not be rolled back",
+                        cause,
+                        n,
+                        n + rolled_back.len()
+                    )?,
+                }
+                for (to, from) in rolled_back {
+                    write!(f, "\n\trenamed {} back to {}", to, from)?;
+                }
+                for x in failed {
+                    write!(f, "\n\tERR: {}", x)?;
+                }
+                Ok(())
+            }
         }
     }
 }
+
+// Renders s with carets under the len characters starting at pos.
+fn caret(s: &str, pos: usize, len: usize) -> String {
+    format!(
+        "\n\t{}\n\t{}{}\n",
+        s,
+        " ".repeat(pos),
+        "^".repeat(len.max(1))
+    )
+}
@@ -1,199 +1,769 @@
+use crate::calendar::{self, UtcOffset};
 use crate::constants as c;
-use crate::format_parser::Node;
-use crate::error::{MyCustomError};
+use crate::error::MyCustomError;
+use crate::format_parser::{Fragment, Hole, Node};
+use crate::locale::Locale;
+use std::collections::{HashMap, HashSet};
 
 // Internal representation.
+#[derive(Clone, PartialEq)]
 pub struct Data {
-    pub year: Option<Year>,
+    pub year: Option<i32>,
     pub month: Option<c::Months>,
-    pub day: Option<String>,
-    pub holes: Vec<String>,
+    pub day: Option<u32>,
+    pub hour: Option<u32>,
+    pub minute: Option<u32>,
+    pub second: Option<u32>,
+    pub millisecond: Option<u32>,
+    pub weekday: Option<c::Weekdays>,
+    // The year as written in the filename if it has only two digits.
+    pub short_year: Option<u32>,
+    pub holes: HashMap<Fragment, String>,
+}
+
+impl Data {
+    // Returns the day of the week, computed from the date if it is known and
+    // taken from the filename otherwise.
+    pub fn weekday(&self) -> Option<c::Weekdays> {
+        match (self.year, self.month, self.day) {
+            (Some(y), Some(m), Some(d)) => Some(calendar::weekday(y, calendar::month_number(m), d)),
+            _ => self.weekday,
+        }
+    }
+
+    // Describes the values which differ between self and other.
+    pub fn differences(&self, other: &Data) -> (String, String) {
+        let (mut a, mut b) = (Vec::new(), Vec::new());
+        let mut diff = |name: String, x: Option<String>, y: Option<String>| {
+            if x != y {
+                let none = || "none".to_string();
+                a.push(format!("{} {}", name, x.unwrap_or_else(none)));
+                b.push(format!("{} {}", name, y.unwrap_or_else(none)));
+            }
+        };
+        let s = |x: Option<u32>| x.map(|v| v.to_string());
+        diff(
+            "year".to_string(),
+            self.year.map(|v| v.to_string()),
+            other.year.map(|v| v.to_string()),
+        );
+        diff(
+            "month".to_string(),
+            self.month.map(|v| v.to_string()),
+            other.month.map(|v| v.to_string()),
+        );
+        diff("day".to_string(), s(self.day), s(other.day));
+        diff("hour".to_string(), s(self.hour), s(other.hour));
+        diff("minute".to_string(), s(self.minute), s(other.minute));
+        diff("second".to_string(), s(self.second), s(other.second));
+        diff(
+            "weekday".to_string(),
+            self.weekday.map(|v| v.to_string()),
+            other.weekday.map(|v| v.to_string()),
+        );
+        let mut fragments: Vec<&Fragment> = self.holes.keys().chain(other.holes.keys()).collect();
+        fragments.sort();
+        fragments.dedup();
+        for f in fragments {
+            let quote = |v: &String| format!("\"{}\"", v);
+            diff(
+                f.to_string(),
+                self.holes.get(f).map(quote),
+                other.holes.get(f).map(quote),
+            );
+        }
+        (a.join(", "), b.join(", "))
+    }
+}
+
+// Options which change how filenames are read.
+#[derive(Default)]
+pub struct ParseOpt {
+    // Language of month and weekday names.
+    pub locale: Locale,
+    // Further names of months, e.g. read from a config file.
+    pub month_aliases: Vec<(String, c::Months)>,
+    // Two-digit years above the pivot are read as 19xx, all others as 20xx.
+    pub pivot_year: Option<u32>,
+    // Offset of the local time from UTC for epoch timestamps.
+    pub epoch_offset: UtcOffset,
+    // Literals match regardless of case.
+    pub ignore_case: bool,
 }
 
 pub struct FileNameParser<'b> {
     pub name: std::iter::Peekable<std::str::Chars<'b>>,
     pub d: Data,
+    input: &'b str,
+    opt: &'b ParseOpt,
+
+    pending: Pending,
 }
 
-type Result<T> = std::result::Result<(), T>;
+// The part of a pattern which is left to match after the current group.
+struct Rest<'a> {
+    nodes: &'a [Node],
+    next: Option<&'a Rest<'a>>,
+}
+
+// The state of the search for all ways in which a filename matches a pattern.
+#[derive(Default)]
+struct Search {
+    // The choices made so far at optional groups, alternatives and identifiers
+    // of variable width. Earlier choices are preferred, e.g. an optional group
+    // which is present over one which is absent.
+    path: Vec<usize>,
+    // The first match and its choices.
+    best: Option<(Data, Vec<usize>)>,
+    // A match with the same choices as the best one but different values,
+    // which happens if holes can be split in more than one way.
+    other: Option<Data>,
+    // The error of the attempt that got furthest into the filename.
+    error: Option<(usize, MyCustomError)>,
+    // The first match that was rejected because of an invalid date.
+    invalid: Option<MyCustomError>,
+    // The number of attempts which read the whole name.
+    ends: usize,
+    // The parts of the pattern which cannot read the rest of the name from a
+    // position. Whether nodes match depends only on the position, so these are
+    // not searched again, which would take exponential time for adjacent holes.
+    dead: HashSet<(Vec<(usize, usize)>, usize)>,
+}
+
+// Values which only make up a date or time together with others. They are
+// resolved into d once the whole name is parsed.
+#[derive(Clone, Default)]
+struct Pending {
+    // 12-hour clock.
+    hour12: Option<u32>,
+    pm: Option<bool>,
+    // ISO 8601 week-year and week.
+    iso_year: Option<i32>,
+    week: Option<u32>,
+    quarter: Option<u32>,
+    day_of_year: Option<u32>,
+}
 
-type Year = [char; 4];
+type Result<T> = std::result::Result<(), T>;
 
 impl FileNameParser<'_> {
-    pub fn new(s: &str, num_holes: usize) -> FileNameParser {
-        return FileNameParser {
+    pub fn new<'b>(s: &'b str, num_holes: usize, opt: &'b ParseOpt) -> FileNameParser<'b> {
+        FileNameParser {
             name: s.chars().peekable(),
+            input: s,
+            opt,
             d: Data {
                 year: None,
                 month: None,
                 day: None,
-                holes: vec![String::from(""); num_holes],
+                hour: None,
+                minute: None,
+                second: None,
+                millisecond: None,
+                weekday: None,
+                short_year: None,
+                holes: HashMap::with_capacity(num_holes),
             },
-        };
+            pending: Pending::default(),
+        }
     }
-    pub fn parse(&mut self, template_nodes: &Vec<Node>) -> Result<MyCustomError> {
-        let mut i: usize = 0;
-        let mut nodes = template_nodes.iter().peekable();
-        loop {
-            i = i + 1;
-            match nodes.next() {
-                Some(n) => match n {
-                    Node::Year => self.parse_year(4)?,
-                    Node::YearShort => self.parse_year(2)?,
-                    Node::FebNum => self.parse_month(2)?,
-                    Node::Feb => self.parse_month(3)?,
-                    Node::FebAllCaps => self.parse_month(3)?,
-                    Node::FebCaps => self.parse_month(3)?,
-                    Node::February => self.parse_month_flex(stop_char(nodes.peek()))?,
-                    Node::FebruaryAllCaps => self.parse_month_flex(stop_char(nodes.peek()))?,
-                    Node::FebruaryCaps => self.parse_month_flex(stop_char(nodes.peek()))?,
-                    Node::Day => self.parse_day(2)?,
-                    Node::Hole(i) => self.parse_hole(*i, stop_char(nodes.peek()))?,
-                    Node::Literal(s) => self.parse_literal(s)?,
-                },
-                None => {
-                    if self.name.peek() == None {
-                        return Ok(());
-                    }
-                    return Err(MyCustomError::ParseError("filename does not match pattern".to_string()));
-                }
-            };
+    // Matches the filename against the pattern. All ways to match are
+    // searched, so that a filename which can be read in more than one way is
+    // rejected instead of renamed by guesswork.
+    pub fn parse(&mut self, template_nodes: &[Node]) -> Result<MyCustomError> {
+        let mut search = Search::default();
+        self.search(template_nodes, None, &mut search);
+        match (search.best, search.other) {
+            (Some((d, _)), None) => {
+                self.d = d;
+                Ok(())
+            }
+            (Some((a, _)), Some(b)) => {
+                let (x, y) = a.differences(&b);
+                Err(MyCustomError::AmbiguousMatchError(
+                    self.input.to_string(),
+                    x,
+                    y,
+                ))
+            }
+            (None, _) => Err(search
+                .invalid
+                .or(search.error.map(|e| e.1))
+                .unwrap_or_else(|| {
+                    MyCustomError::ParseError("filename does not match pattern".to_string())
+                })),
         }
     }
 
-    fn parse_year(&mut self, i: u32) -> Result<&'static str> {
-        if i != 2 && i != 4 {
-            return Err("supported formats for year are 08 and 2008");
+    // Matches nodes and then the rest of the pattern, unless they are known
+    // not to match from the current position.
+    fn search(&mut self, nodes: &[Node], next: Option<&Rest>, search: &mut Search) {
+        let key = (continuation(nodes, next), self.pos());
+        if search.dead.contains(&key) {
+            return;
         }
-        let mut year: Year = ['0'; 4];
-        let mut cursor: usize = 0;
-        if i == 2 {
-            cursor += 2;
-            year[0] = '2';
-            year[1] = '0';
+        // Once a match is found, choices which differ from it are skipped, so
+        // only searches before that are complete.
+        let (ends, complete) = (search.ends, search.best.is_none());
+        self.search_nodes(nodes, next, search);
+        if complete && search.ends == ends {
+            search.dead.insert(key);
         }
-        let mut err: &str = "";
-        for _ in 0..i {
-            match self.name.next() {
-                Some(x) => {
-                    if x.is_numeric() {
-                        year[cursor]=x;
-                        cursor+=1;
+    }
+
+    // Matches nodes and then the rest of the pattern, trying every choice of
+    // every node in turn.
+    fn search_nodes(&mut self, nodes: &[Node], next: Option<&Rest>, search: &mut Search) {
+        let (n, nodes) = match nodes.split_first() {
+            Some(x) => x,
+            None => {
+                match next {
+                    Some(r) => self.search(r.nodes, r.next, search),
+                    None => self.complete(search),
+                }
+                return;
+            }
+        };
+        let rest = Rest { nodes, next };
+        let saved = (self.name.clone(), self.d.clone(), self.pending.clone());
+        for choice in 0..self.choices(n) {
+            if search.other.is_some() {
+                break;
+            }
+            self.name = saved.0.clone();
+            self.d = saved.1.clone();
+            self.pending = saved.2.clone();
+            // Holes and numbers without leading zero are not part of the
+            // path, so that different splits of the same choices are found as
+            // ambiguous, e.g. 112 read as 1 12 and 11 2.
+            let hole = matches!(
+                n,
+                Node::Hole(_) | Node::DayUnpadded | Node::DayOrdinal | Node::FebNumUnpadded
+            );
+            if !hole {
+                let k = search.path.len();
+                if let Some((_, best)) = &search.best {
+                    if best.get(k).is_some_and(|x| *x != choice) {
                         continue;
                     }
-                    return Err("character can not be converted to a numeric");
                 }
-                None => err = "template does not match string. Most likely, the template contains too many placeholders",
+                search.path.push(choice);
             }
+            match n {
+                Node::Optional(group) if choice == 0 => self.search(group, Some(&rest), search),
+                Node::Optional(group) => {
+                    clear_holes(group, &mut self.d);
+                    self.search(rest.nodes, rest.next, search)
+                }
+                Node::Alternation(branches) => self.search(&branches[choice], Some(&rest), search),
+                Node::Literal(l) => {
+                    let pos = self.pos();
+                    match self.parse_literal(l) {
+                        Ok(()) => self.search(rest.nodes, rest.next, search),
+                        Err(e) => record(search, pos, e),
+                    }
+                }
+                _ => match self.parse_node(n, choice) {
+                    Ok(()) => self.search(rest.nodes, rest.next, search),
+                    Err(e) => self.fail(search, e),
+                },
+            }
+            if !hole {
+                search.path.pop();
+            }
+        }
+    }
+
+    // Records a match once the whole pattern is matched.
+    fn complete(&mut self, search: &mut Search) {
+        if self.name.peek().is_some() {
+            let pos = self.pos();
+            let e = self.mismatch(pos, "the end of the name", self.name.clone().collect());
+            return record(search, pos, e);
+        }
+        search.ends += 1;
+        if let Err(e) = self.resolve_hour12() {
+            return self.fail(search, e);
+        }
+        if let Err(e) = self.resolve_date().and_then(|_| self.validate()) {
+            search.invalid.get_or_insert(e);
+            return;
         }
-        if err != "" {
-            return Err(err);
+        match &search.best {
+            None => search.best = Some((self.d.clone(), search.path.clone())),
+            Some((d, path)) if *path == search.path && *d != self.d => {
+                search.other = Some(self.d.clone())
+            }
+            Some(_) => (),
         }
-        self.d.year = Some(year);
-        return Ok(());
     }
 
-    fn parse_month(&mut self, i: u32) -> Result<&'static str> {
-        let mut month = String::from("");
-        let mut err: &str = "";
-        for _ in 0..i {
-            match self.name.next() {
-                Some(x) => month.push(x),
-                None => err = "This should not happen",
+    fn fail(&self, search: &mut Search, reason: &'static str) {
+        record(
+            search,
+            self.pos(),
+            MyCustomError::ParseError(reason.to_string()),
+        );
+    }
+
+    // Returns the number of characters read so far.
+    fn pos(&self) -> usize {
+        self.input.chars().count() - self.name.clone().count()
+    }
+
+    fn mismatch(&self, pos: usize, expected: &str, found: String) -> MyCustomError {
+        MyCustomError::MismatchError(self.input.to_string(), pos, expected.to_string(), found)
+    }
+
+    // Returns the number of ways in which n can match at the current position.
+    fn choices(&self, n: &Node) -> usize {
+        let count = match n {
+            Node::Optional(_) => 2,
+            Node::Alternation(branches) => branches.len(),
+            // A hole takes at least one character.
+            Node::Hole(h) if h.width.is_none() => self
+                .name
+                .clone()
+                .take_while(|x| h.class.contains(*x))
+                .count(),
+            // One or two digits.
+            Node::DayUnpadded | Node::DayOrdinal | Node::FebNumUnpadded => 2,
+            Node::Feb | Node::FebAllCaps | Node::FebCaps => self.month_names(false).len(),
+            Node::February | Node::FebruaryAllCaps | Node::FebruaryCaps => {
+                self.month_names(true).len()
+            }
+            Node::Sat | Node::SatCaps | Node::SatAllCaps => self.weekday_names(false).len(),
+            Node::Saturday | Node::SaturdayCaps | Node::SaturdayAllCaps => {
+                self.weekday_names(true).len()
+            }
+            _ => 1,
+        };
+        // Nodes without any way to match are tried once to report the error.
+        count.max(1)
+    }
+
+    // Matches a single node in the way given by choice.
+    fn parse_node(&mut self, n: &Node, choice: usize) -> Result<&'static str> {
+        match n {
+            Node::Year => self.parse_year(4)?,
+            Node::YearShort => self.parse_year(2)?,
+            Node::FebNum => self.parse_month()?,
+            Node::Feb | Node::FebAllCaps | Node::FebCaps => self.parse_month_name(false, choice)?,
+            Node::February | Node::FebruaryAllCaps | Node::FebruaryCaps => {
+                self.parse_month_name(true, choice)?
+            }
+            Node::Day => self.parse_day(2)?,
+            Node::DayUnpadded => self.d.day = Some(self.parse_unpadded(choice)?),
+            Node::DayOrdinal => self.parse_ordinal(choice)?,
+            Node::FebNumUnpadded => self.parse_month_unpadded(choice)?,
+            Node::Hole(h) => self.parse_hole(h, choice + 1)?,
+            Node::Hour => self.d.hour = Some(self.parse_number(2)?),
+            Node::Hour12 => self.pending.hour12 = Some(self.parse_number(2)?),
+            Node::IsoYear => self.pending.iso_year = Some(self.parse_number(4)? as i32),
+            Node::IsoWeek => {
+                self.parse_prefix('W')?;
+                self.pending.week = Some(self.parse_number(2)?)
+            }
+            Node::Quarter => {
+                self.parse_prefix('Q')?;
+                self.pending.quarter = Some(self.parse_number(1)?)
+            }
+            Node::DayOfYear => self.pending.day_of_year = Some(self.parse_number(3)?),
+            Node::EpochSeconds => {
+                let t = self.parse_long_number(10)?;
+                self.set_timestamp(t as i64);
+            }
+            Node::EpochMillis => {
+                let t = self.parse_long_number(13)?;
+                self.set_timestamp((t / 1000) as i64);
+                self.d.millisecond = Some((t % 1000) as u32);
+            }
+            Node::Minute => self.d.minute = Some(self.parse_number(2)?),
+            Node::Second => self.d.second = Some(self.parse_number(2)?),
+            Node::Pm | Node::PmAllCaps => self.parse_pm()?,
+            Node::Sat | Node::SatCaps | Node::SatAllCaps => self.parse_weekday(false, choice)?,
+            Node::Saturday | Node::SaturdayCaps | Node::SaturdayAllCaps => {
+                self.parse_weekday(true, choice)?
+            }
+            // Existing sequence numbers are skipped.
+            Node::Counter(i) => {
+                self.parse_number(*i as u32)?;
+            }
+            // Groups and literals are matched by search.
+            Node::Optional(_) | Node::Alternation(_) | Node::Literal(_) => (),
+        };
+        Ok(())
+    }
+
+    fn parse_year(&mut self, i: u32) -> Result<&'static str> {
+        if i != 2 && i != 4 {
+            return Err("supported formats for year are 08 and 2008");
+        }
+        let year = self.parse_number(i)?;
+        if i == 2 {
+            let century = match self.opt.pivot_year {
+                Some(p) if year > p => 1900,
+                _ => 2000,
             };
+            self.d.year = Some(century + year as i32);
+            self.d.short_year = Some(year);
+        } else {
+            self.d.year = Some(year as i32);
+        }
+        Ok(())
+    }
+
+    fn parse_month(&mut self) -> Result<&'static str> {
+        match calendar::month_from_number(self.parse_number(2)?) {
+            Some(m) => {
+                self.d.month = Some(m);
+                Ok(())
+            }
+            None => Err("unknown month"),
         }
-        if err != "" {
-            return Err(err);
+    }
+
+    fn parse_month_unpadded(&mut self, choice: usize) -> Result<&'static str> {
+        match calendar::month_from_number(self.parse_unpadded(choice)?) {
+            Some(m) => {
+                self.d.month = Some(m);
+                Ok(())
+            }
+            None => Err("unknown month"),
         }
-        if c::TOIR.contains_key(&month[..]) {
-            self.d.month = Some(c::TOIR[&month[..]]);
-            return Ok(());
+    }
+
+    // Returns the months whose names start at the current position and the
+    // number of characters of each name, longest first. A name may end with a
+    // dot, e.g. Sept., which is preferred over the name without the dot.
+    fn month_names(&self, long: bool) -> Vec<(c::Months, usize)> {
+        let rest: String = self.name.clone().collect();
+        let names = self
+            .opt
+            .locale
+            .match_month(&rest, long, &self.opt.month_aliases);
+        with_dots(&rest, names)
+    }
+
+    fn weekday_names(&self, long: bool) -> Vec<(c::Weekdays, usize)> {
+        let rest: String = self.name.clone().collect();
+        with_dots(&rest, self.opt.locale.match_weekday(&rest, long))
+    }
+
+    // Reads the name of a month in the source locale. Abbreviations are
+    // accepted for both short and long names.
+    fn parse_month_name(&mut self, long: bool, choice: usize) -> Result<&'static str> {
+        match self.month_names(long).get(choice) {
+            Some((m, len)) => {
+                self.d.month = Some(*m);
+                self.skip(*len);
+                Ok(())
+            }
+            None => Err("unknown month"),
         }
-        Err("unknown month")
     }
 
-    fn parse_month_flex(&mut self, stop: char) -> Result<&'static str> {
-        let mut month = String::from("");
-        loop {
-            match self.name.peek() {
-                Some(x) if *x == stop => break,
-                Some(x) => {
-                    month.push(*x);
-                    self.name.next();
-                }
-                None => break,
+    // Reads the name of a weekday in the source locale.
+    fn parse_weekday(&mut self, long: bool, choice: usize) -> Result<&'static str> {
+        match self.weekday_names(long).get(choice) {
+            Some((w, len)) => {
+                self.d.weekday = Some(*w);
+                self.skip(*len);
+                Ok(())
             }
+            None => Err("unknown weekday"),
         }
-        let lc = month.to_lowercase();
-        if c::TOIR.contains_key(&lc[..]) {
-            self.d.month = Some(c::TOIR[&lc[..]]);
-            return Ok(());
+    }
+
+    // Reads the letter in front of a week or quarter, e.g. the W of W22.
+    fn parse_prefix(&mut self, prefix: char) -> Result<&'static str> {
+        match self.name.next() {
+            Some(x) if x.eq_ignore_ascii_case(&prefix) => Ok(()),
+            _ => Err("expected W of week or Q of quarter"),
+        }
+    }
+
+    fn skip(&mut self, n: usize) {
+        for _ in 0..n {
+            self.name.next();
         }
-        Err("unknown month")
     }
 
     fn parse_day(&mut self, i: u32) -> Result<&'static str> {
-        let mut day = String::from("");
-        let mut err: &str = "";
+        self.d.day = Some(self.parse_number(i)?);
+        Ok(())
+    }
+
+    // Reads a number of two digits for the first choice and of one digit for
+    // the second.
+    fn parse_unpadded(&mut self, choice: usize) -> std::result::Result<u32, &'static str> {
+        self.parse_number(2 - choice as u32)
+    }
+
+    // Reads a day followed by its ordinal suffix, e.g. 1st or 22nd.
+    fn parse_ordinal(&mut self, choice: usize) -> Result<&'static str> {
+        let day = self.parse_unpadded(choice)?;
+        let suffix: String = self.name.by_ref().take(2).collect();
+        if suffix.to_lowercase() != calendar::ordinal_suffix(day) {
+            return Err("day does not have a valid ordinal suffix");
+        }
+        self.d.day = Some(day);
+        Ok(())
+    }
+
+    // Sets date and time from seconds since January 1, 1970 UTC, shifted to
+    // local time.
+    fn set_timestamp(&mut self, t: i64) {
+        let t = t + self.opt.epoch_offset.0 as i64;
+        let (y, m, d) = calendar::date_from_days(t.div_euclid(86400));
+        let seconds = t.rem_euclid(86400) as u32;
+        self.d.year = Some(y);
+        self.d.month = calendar::month_from_number(m);
+        self.d.day = Some(d);
+        self.d.hour = Some(seconds / 3600);
+        self.d.minute = Some(seconds / 60 % 60);
+        self.d.second = Some(seconds % 60);
+    }
+
+    // Reads exactly i digits.
+    fn parse_number(&mut self, i: u32) -> std::result::Result<u32, &'static str> {
+        self.parse_long_number(i).map(|n| n as u32)
+    }
+
+    fn parse_long_number(&mut self, i: u32) -> std::result::Result<u64, &'static str> {
+        let mut n: u64 = 0;
         for _ in 0..i {
             match self.name.next() {
-                Some(x) => day.push(x),
-                None => err = "This should not happen",
-            };
+                Some(x) => match x.to_digit(10) {
+                    Some(d) => n = n * 10 + d as u64,
+                    None => return Err("character can not be converted to a numeric"),
+                },
+                None => return Err("template does not match string. Most likely, the template contains too many placeholders"),
+            }
         }
-        if err != "" {
-            return Err(err);
+        Ok(n)
+    }
+
+    fn parse_pm(&mut self) -> Result<&'static str> {
+        let pm: String = self.name.by_ref().take(2).collect();
+        match &pm.to_lowercase()[..] {
+            "am" => self.pending.pm = Some(false),
+            "pm" => self.pending.pm = Some(true),
+            _ => return Err("expected AM or PM"),
         }
-        self.d.day = Some(day);
         Ok(())
     }
 
-    // Literals of the original string are ignored.
-    fn parse_literal(&mut self, s: &str) -> Result<&'static str> {
-        if s.len() == 0 {
-            return Err("empty literal");
-        }
-        for _ in 0..s.len() {
-            self.name.next();
+    // Converts a 12-hour clock reading to the 24-hour clock.
+    fn resolve_hour12(&mut self) -> Result<&'static str> {
+        if let Some(h) = self.pending.hour12 {
+            if h == 0 || h > 12 {
+                return Err("hour of a 12-hour clock must be between 01 and 12");
+            }
+            match self.pending.pm {
+                Some(pm) => self.d.hour = Some(h % 12 + if pm { 12 } else { 0 }),
+                None => return Err("12-hour clock requires AM or PM"),
+            }
         }
         Ok(())
     }
 
-    fn parse_hole(&mut self, i: u32, stop: char) -> Result<&'static str> {
-        let mut hole = String::from("");
-        loop {
-            match self.name.peek() {
-                Some(x) if *x == stop => break Ok(()),
-                Some(x) => {
-                    hole.push(*x);
-                    self.name.next();
+    // Turns an ISO week, a day of the year or a quarter into a calendar date.
+    // A week without weekday stands for its Monday and a quarter for its first
+    // day.
+    fn resolve_date(&mut self) -> Result<MyCustomError> {
+        let invalid = |reason: String| {
+            Err(MyCustomError::InvalidDateError(
+                self.input.to_string(),
+                reason,
+            ))
+        };
+        let p = &self.pending;
+        let date = if let Some(w) = p.week {
+            let year = match p.iso_year.or(self.d.year) {
+                Some(y) => y,
+                None => return invalid("week requires a year".to_string()),
+            };
+            let weekday = self.d.weekday.unwrap_or(c::Weekdays::Mon);
+            match calendar::date_from_iso_week(year, w, weekday) {
+                Some(x) => x,
+                None => return invalid(format!("{} has no week {:02}", year, w)),
+            }
+        } else if let Some(n) = p.day_of_year {
+            let year = match self.d.year {
+                Some(y) => y,
+                None => return invalid("day of the year requires a year".to_string()),
+            };
+            match calendar::date_from_day_of_year(year, n) {
+                Some((m, d)) => (year, m, d),
+                None => return invalid(format!("{} has no day {:03}", year, n)),
+            }
+        } else if let Some(q) = p.quarter {
+            if q == 0 || q > 4 {
+                return invalid(format!("quarter {} does not exist", q));
+            }
+            match self.d.month {
+                Some(m) if calendar::quarter(calendar::month_number(m)) != q => {
+                    return invalid(format!("{} is not in quarter {}", m, q))
+                }
+                // The quarter only stands for the first day of its month.
+                Some(_) => {
+                    self.d.day = self.d.day.or(Some(1));
+                    return Ok(());
                 }
                 None => {
-                    if stop == '%' {
-                        break Ok(());
-                    }
-                    break Err("could not parse hole");
+                    self.d.month = calendar::month_from_number(3 * q - 2);
+                    self.d.day = self.d.day.or(Some(1));
+                    return Ok(());
                 }
             }
-        }?;
-        self.d.holes[i as usize] = hole;
+        } else {
+            return Ok(());
+        };
+        let (y, m, d) = date;
+        let m = calendar::month_from_number(m).unwrap();
+        if self.d.year.is_some_and(|x| x != y)
+            || self.d.month.is_some_and(|x| x != m)
+            || self.d.day.is_some_and(|x| x != d)
+        {
+            return invalid(format!("date does not match {} {:02} {}", m, d, y));
+        }
+        self.d.year = Some(y);
+        self.d.month = Some(m);
+        self.d.day = Some(d);
+        Ok(())
+    }
+
+    // Checks that the parsed date exists in the calendar and that the time of
+    // day is valid.
+    fn validate(&self) -> Result<MyCustomError> {
+        let invalid = |reason: String| {
+            Err(MyCustomError::InvalidDateError(
+                self.input.to_string(),
+                reason,
+            ))
+        };
+        if let Some(d) = self.d.day {
+            let max = match (self.d.year, self.d.month) {
+                (Some(y), Some(m)) => calendar::days_in_month(y, calendar::month_number(m)),
+                // February 29 is valid as long as the year is unknown.
+                (None, Some(m)) => calendar::days_in_month(2000, calendar::month_number(m)),
+                (_, None) => 31,
+            };
+            if d == 0 || d > max {
+                return match (self.d.year, self.d.month) {
+                    (Some(y), Some(m)) => invalid(format!("{} {} has no day {:02}", m, y, d)),
+                    (None, Some(m)) => invalid(format!("{} has no day {:02}", m, d)),
+                    (_, None) => invalid(format!("day {:02} does not exist", d)),
+                };
+            }
+        }
+        if let (Some(w), Some(computed)) = (self.d.weekday, self.d.weekday()) {
+            if w != computed {
+                return invalid(format!("date is a {}, not a {}", computed, w));
+            }
+        }
+        if let Some(h) = self.d.hour {
+            if h > 23 {
+                return invalid(format!("hour {:02} does not exist", h));
+            }
+        }
+        if let Some(m) = self.d.minute {
+            if m > 59 {
+                return invalid(format!("minute {:02} does not exist", m));
+            }
+        }
+        if let Some(s) = self.d.second {
+            if s > 59 {
+                return invalid(format!("second {:02} does not exist", s));
+            }
+        }
         Ok(())
     }
+
+    // Literals have to match exactly, or up to case if ignore_case is set.
+    fn parse_literal(&mut self, s: &str) -> Result<MyCustomError> {
+        let pos = self.pos();
+        let found: String = self.name.by_ref().take(s.chars().count()).collect();
+        let same = if self.opt.ignore_case {
+            found.to_lowercase() == s.to_lowercase()
+        } else {
+            found == s
+        };
+        if !same {
+            return Err(self.mismatch(pos, &format!("\"{}\"", s), found));
+        }
+        Ok(())
+    }
+
+    // Reads a hole of len characters, or of its width if it has one.
+    fn parse_hole(&mut self, h: &Hole, len: usize) -> Result<&'static str> {
+        let len = h.width.unwrap_or(len);
+        let hole: String = self.name.by_ref().take(len).collect();
+        if hole.chars().count() != len {
+            return Err("hole does not match its width");
+        }
+        if !hole.chars().all(|x| h.class.contains(x)) {
+            return Err("hole does not match its character class");
+        }
+        self.d.holes.insert(h.fragment.clone(), hole);
+        Ok(())
+    }
+}
+
+// Identifies the nodes left to match by the slices of the pattern they are in.
+fn continuation(nodes: &[Node], mut next: Option<&Rest>) -> Vec<(usize, usize)> {
+    let mut res = vec![(nodes.as_ptr() as usize, nodes.len())];
+    while let Some(r) = next {
+        res.push((r.nodes.as_ptr() as usize, r.nodes.len()));
+        next = r.next;
+    }
+    res
+}
+
+// Keeps the error of the attempt that got furthest into the filename.
+fn record(search: &mut Search, pos: usize, e: MyCustomError) {
+    if search.error.as_ref().is_none_or(|(p, _)| pos > *p) {
+        search.error = Some((pos, e));
+    }
 }
 
-fn stop_char(opt: Option<&&Node>) -> char {
-    match opt {
-        Some(x) => match x {
-            Node::Literal(a) => a.chars().next().unwrap(),
-            _ => panic!("node cannot provide stop char"),
-        },
-        None => '%',
+// Adds the variant with a trailing dot in front of every name which is
+// followed by a dot in s.
+fn with_dots<T: Copy>(s: &str, names: Vec<(T, usize)>) -> Vec<(T, usize)> {
+    let mut res = Vec::new();
+    for (x, len) in names {
+        if s.chars().nth(len) == Some('.') {
+            res.push((x, len + 1));
+        }
+        res.push((x, len));
+    }
+    res
+}
+
+fn clear_holes(nodes: &[Node], d: &mut Data) {
+    for n in nodes {
+        match n {
+            Node::Hole(h) => {
+                d.holes.insert(h.fragment.clone(), String::from(""));
+            }
+            Node::Optional(group) => clear_holes(group, d),
+            Node::Alternation(branches) => {
+                for b in branches {
+                    clear_holes(b, d);
+                }
+            }
+            _ => (),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::calendar::UtcOffset;
     use crate::constants as c;
+    use crate::filename_parser::ParseOpt;
+    use crate::format_parser::Fragment;
     use crate::FileNameParser;
     use crate::FormatParser;
     use crate::MyCustomError;
@@ -205,7 +775,8 @@ mod tests {
                     let mut source = FormatParser::new($source_template);
                     source.parse()?;
 
-                    let mut parser = FileNameParser::new($s, source.holes);
+                    let opt = ParseOpt::default();
+                    let mut parser = FileNameParser::new($s, source.holes, &opt);
                     let res = parser.parse(&source.nodes);
 
                     if $want_error {
@@ -229,6 +800,122 @@ mod tests {
 
     testify!(works1, "%0", "hello", false);
     testify!(works2, "%0_%1", "hello_world", false);
+    testify!(works3, "%{client}_%{title}", "acme_invoice", false);
+
+    testify!(weekday1, "sat_%0", "Mon_standup", false);
+    testify!(weekday2, "saturday_%0", "wednesday_standup", false);
+    testify!(weekday3, "SAT_%0", "Xyz_standup", true);
+    testify!(weekday4, "Saturday", "Monkey", true);
+    testify!(weekday5, "%0_compensation", "x_compenmonion", true);
+
+    testify!(
+        optional1,
+        "2003-02-01_%0[_%1]",
+        "2021-06-01_acme_invoice",
+        false
+    );
+    testify!(optional2, "2003-02-01_%0[_%1]", "2021-06-01_acme", false);
+    testify!(optional3, "[%0_]2003", "2021", false);
+    testify!(optional4, "[%0_]2003", "x_2021", false);
+    testify!(optional5, "%0[-v%1]_2003", "report_2021", false);
+    testify!(optional6, "%0[-v%1]_2003", "report-v2_2021", false);
+    testify!(optional7, "2003[_%0]", "2021-x", true);
+
+    testify!(
+        alternation1,
+        "(01.02.2003|2003-02-01)_%0",
+        "01.06.2021_foo",
+        false
+    );
+    testify!(
+        alternation2,
+        "(01.02.2003|2003-02-01)_%0",
+        "2021-06-01_foo",
+        false
+    );
+    testify!(
+        alternation3,
+        "(01.02.2003|2003-02-01)_%0",
+        "2021.06.01_foo",
+        true
+    );
+    testify!(alternation4, "%0(_|-)2003", "foo-2021", false);
+
+    testify!(class1, "INV%0:d%1:a", "INV12345ACME", false);
+    testify!(class2, "INV%0:d%1:a", "INV12345", true);
+    testify!(class3, "INV%0:d%1:a", "INVACME", true);
+    testify!(class4, "%0:{4}%1", "2021report", false);
+    testify!(class5, "%0:{4}%1", "202", true);
+    testify!(class6, "%0:d{4}%1", "20x1report", true);
+    testify!(class7, "%0:[A-Z]%1:[0-9]_%2", "ABC123_x", false);
+    testify!(class8, "%0:[A-Z]%1:[0-9]_%2", "ABC_x", true);
+
+    testify!(counter1, "2003-02-01_###", "2021-06-01_001", false);
+    testify!(counter2, "2003-02-01_###", "2021-06-01_01", true);
+
+    testify!(unpadded1, "1-2-2003", "1-6-2021", false);
+    testify!(unpadded2, "1-2-2003", "12-10-2021", false);
+    testify!(unpadded3, "1-2-2003", "1-13-2021", true);
+    testify!(unpadded4, "1-2-2003", "-6-2021", true);
+    testify!(ordinal1, "February 1st 2003", "June 1st 2021", false);
+    testify!(ordinal2, "February 1st 2003", "June 22ND 2021", false);
+    testify!(ordinal3, "February 1st 2003", "June 11st 2021", true);
+    testify!(ordinal4, "February 1st 2003", "June 31st 2021", true);
+
+    testify!(month1, "feb_01_2003", "jun_01_2021", false);
+    testify!(month2, "feb_01_2003", "JUN_01_2021", false);
+    testify!(month3, "feb_01_2003", "Sept._01_2021", false);
+    testify!(month4, "01.feb.2003", "01.Sept.2021", false);
+    testify!(month5, "01.feb.2003", "01.Sept..2021", false);
+    testify!(month7, "01.feb.2003", "01.Sept...2021", true);
+    testify!(month6, "february_01_2003", "Jan._01_2021", false);
+
+    testify!(week1, "2003-W05", "2021-W22", false);
+    testify!(week2, "2003-W05", "2021-w53", true);
+    testify!(week3, "2003-W05", "2020-W53", false);
+    testify!(week4, "2003-W05", "2021-22", true);
+    testify!(week5, "2003-W05_sat", "2021-W22_Fri", false);
+    testify!(quarter1, "2003-Q1", "2021-Q2", false);
+    testify!(quarter2, "2003-Q1", "2021-Q5", true);
+    testify!(quarter3, "2003-02_Q1", "2021-06_Q3", true);
+    testify!(day_of_year1, "2003-032", "2021-152", false);
+    testify!(day_of_year2, "2003-032", "2021-366", true);
+    testify!(day_of_year3, "2003-032", "2020-366", false);
+
+    testify!(epoch1, "1044122645_%0", "1622548800_backup", false);
+    testify!(epoch2, "1044122645_%0", "162254880_backup", true);
+    testify!(epoch3, "1044122645000", "1622548800123", false);
+
+    testify!(backtrack1, "%0february", "reportjune", false);
+    testify!(backtrack2, "%0--%1", "a-b--c", false);
+    testify!(backtrack3, "%0%1:{2}", "report21", false);
+    testify!(backtrack4, "%0_2003%1", "a_b_2021c", false);
+    testify!(backtrack5, "%0saturday%1:d", "xmonday1", false);
+    testify!(ambiguous1, "%0_%1", "a_b_c", true);
+    testify!(ambiguous2, "%0%1", "ab", false);
+    testify!(ambiguous3, "%0%1", "abc", true);
+
+    testify!(literal1, "%0_2003", "foo-2021", true);
+    testify!(literal2, "%0_2003", "foo_2021", false);
+
+    testify!(date1, "20030201_%0_%1", "20190229_foo_bar", true);
+    testify!(date2, "20030201_%0_%1", "20200229_foo_bar", false);
+    testify!(date3, "20030201", "20210431", true);
+    testify!(date4, "20030201", "20210400", true);
+    testify!(date5, "20030201", "202104ab", true);
+    testify!(date6, "0201", "0229", false);
+    testify!(date7, "0201", "0230", true);
+    testify!(date8, "2003", "20x1", true);
+    testify!(date9, "sat_20030201", "mon_20210607", false);
+    testify!(date10, "sat_20030201", "tue_20210607", true);
+
+    testify!(time1, "180405", "143502", false);
+    testify!(time2, "180405", "243502", true);
+    testify!(time3, "18:04", "14:60", true);
+    testify!(time4, "06:04PM", "02:15am", false);
+    testify!(time5, "06:04", "02:15", true);
+    testify!(time6, "06:04PM", "13:15PM", true);
+    testify!(time7, "06:04PM", "02:15xx", true);
 
     #[test]
     fn test_month_mapping() -> Result<(), MyCustomError> {
@@ -238,7 +925,8 @@ mod tests {
         let mut source = FormatParser::new(&source_template);
         source.parse()?;
 
-        let mut parser = FileNameParser::new(&s, 0);
+        let opt = ParseOpt::default();
+        let mut parser = FileNameParser::new(&s, 0, &opt);
         let res = parser.parse(&source.nodes);
 
         assert!(res.is_ok());
@@ -254,11 +942,244 @@ mod tests {
         let mut source = FormatParser::new(&source_template);
         source.parse()?;
 
-        let mut parser = FileNameParser::new(&s, 0);
+        let opt = ParseOpt::default();
+        let mut parser = FileNameParser::new(&s, 0, &opt);
         let res = parser.parse(&source.nodes);
 
         assert!(res.is_ok());
         assert_eq!(parser.d.month.unwrap(), c::Months::Jun);
         Ok(())
     }
+
+    #[test]
+    fn test_pivot_year() -> Result<(), MyCustomError> {
+        let mut source = FormatParser::new("030201_%0");
+        source.parse()?;
+        for (s, pivot, want) in [
+            ("980312_letter", None, 2098),
+            ("980312_letter", Some(69), 1998),
+            ("690312_letter", Some(69), 2069),
+            ("700312_letter", Some(69), 1970),
+        ] {
+            let opt = ParseOpt {
+                pivot_year: pivot,
+                ..ParseOpt::default()
+            };
+            let mut parser = FileNameParser::new(s, source.holes, &opt);
+            parser.parse(&source.nodes)?;
+            assert_eq!(parser.d.year, Some(want));
+            assert_eq!(parser.d.short_year, Some(want as u32 % 100));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_epoch_offset() -> Result<(), MyCustomError> {
+        let mut source = FormatParser::new("1044122645000");
+        source.parse()?;
+        for (offset, want) in [
+            (0, (2021, c::Months::Jun, 1, 12, 0)),
+            (7200, (2021, c::Months::Jun, 1, 14, 0)),
+            (-43200, (2021, c::Months::Jun, 1, 0, 0)),
+            (-45000, (2021, c::Months::May, 31, 23, 30)),
+        ] {
+            let opt = ParseOpt {
+                epoch_offset: UtcOffset(offset),
+                ..ParseOpt::default()
+            };
+            let mut parser = FileNameParser::new("1622548800123", source.holes, &opt);
+            parser.parse(&source.nodes)?;
+            let d = &parser.d;
+            assert_eq!(
+                (
+                    d.year.unwrap(),
+                    d.month.unwrap(),
+                    d.day.unwrap(),
+                    d.hour.unwrap(),
+                    d.minute.unwrap()
+                ),
+                want
+            );
+            assert_eq!(d.second, Some(0));
+            assert_eq!(d.millisecond, Some(123));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_literal_mismatch() -> Result<(), MyCustomError> {
+        let mut source = FormatParser::new("2003-02-01_%0");
+        source.parse()?;
+        let opt = ParseOpt::default();
+        let cases = [
+            ("2021-06-01xfoo", 10, "\"_\"", "x"),
+            ("2021.06-01_foo", 4, "\"-\"", "."),
+            ("2021-06-01", 10, "\"_\"", ""),
+        ];
+        for (s, pos, expected, found) in cases.iter() {
+            let mut parser = FileNameParser::new(s, source.holes, &opt);
+            match parser.parse(&source.nodes) {
+                Err(MyCustomError::MismatchError(name, p, e, f)) => {
+                    assert_eq!(
+                        (&name[..], p, &e[..], &f[..]),
+                        (*s, *pos, *expected, *found)
+                    )
+                }
+                _ => panic!("expected mismatch for {}", s),
+            }
+        }
+
+        let mut source = FormatParser::new("Scan_2003");
+        source.parse()?;
+        let mut parser = FileNameParser::new("Scän_2021", source.holes, &opt);
+        let e = parser.parse(&source.nodes).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "Scän_2021 does not match the source pattern: expected \"Scan_\" at character 1, found \"Scän_\"\n\n\tScän_2021\n\t^^^^^\n"
+        );
+
+        let mut source = FormatParser::new("20030201_%0_done");
+        source.parse()?;
+        let mut parser = FileNameParser::new("20210601_foo_DONE", source.holes, &opt);
+        assert!(parser.parse(&source.nodes).is_err());
+        let opt = ParseOpt {
+            ignore_case: true,
+            ..ParseOpt::default()
+        };
+        let mut parser = FileNameParser::new("20210601_foo_DONE", source.holes, &opt);
+        parser.parse(&source.nodes)?;
+        assert_eq!(parser.d.holes[&Fragment::Index(0)], "foo");
+        Ok(())
+    }
+
+    #[test]
+    fn test_ambiguous_match() -> Result<(), MyCustomError> {
+        let mut source = FormatParser::new("%0_%1");
+        source.parse()?;
+        let opt = ParseOpt::default();
+        let mut parser = FileNameParser::new("a_b_c", source.holes, &opt);
+        match parser.parse(&source.nodes) {
+            Err(MyCustomError::AmbiguousMatchError(name, a, b)) => {
+                assert_eq!(name, "a_b_c");
+                assert_eq!(a, r#"%0 "a", %1 "b_c""#);
+                assert_eq!(b, r#"%0 "a_b", %1 "c""#);
+            }
+            _ => panic!("expected ambiguous match"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_ambiguous_number() -> Result<(), MyCustomError> {
+        let mut source = FormatParser::new("1%0:d");
+        source.parse()?;
+        let opt = ParseOpt::default();
+        let mut parser = FileNameParser::new("123", source.holes, &opt);
+        assert!(matches!(
+            parser.parse(&source.nodes),
+            Err(MyCustomError::AmbiguousMatchError(..))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_backtracking_values() -> Result<(), MyCustomError> {
+        let mut source = FormatParser::new("%0february%1");
+        source.parse()?;
+        let opt = ParseOpt::default();
+        let mut parser = FileNameParser::new("junejunex", source.holes, &opt);
+        parser.parse(&source.nodes)?;
+        assert_eq!(parser.d.month, Some(c::Months::Jun));
+        assert_eq!(parser.d.holes[&Fragment::Index(0)], "june");
+        assert_eq!(parser.d.holes[&Fragment::Index(1)], "x");
+        Ok(())
+    }
+
+    #[test]
+    fn test_adjacent_holes_long_name() -> Result<(), MyCustomError> {
+        let mut source = FormatParser::new("%0%1%2%3%4_x");
+        source.parse()?;
+        let opt = ParseOpt::default();
+        let name = "a".repeat(60);
+        let start = std::time::Instant::now();
+        let mut parser = FileNameParser::new(&name, source.holes, &opt);
+        assert!(parser.parse(&source.nodes).is_err());
+        assert!(start.elapsed() < std::time::Duration::from_secs(2));
+
+        let name = format!("{}_x", "a".repeat(60));
+        let mut parser = FileNameParser::new(&name, source.holes, &opt);
+        assert!(matches!(
+            parser.parse(&source.nodes),
+            Err(MyCustomError::AmbiguousMatchError(..))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_date_error() -> Result<(), MyCustomError> {
+        let mut source = FormatParser::new("20030201_%0_%1");
+        source.parse()?;
+
+        let opt = ParseOpt::default();
+        let mut parser = FileNameParser::new("20190229_foo_bar", source.holes, &opt);
+        match parser.parse(&source.nodes) {
+            Err(MyCustomError::InvalidDateError(name, reason)) => {
+                assert_eq!(name, "20190229_foo_bar");
+                assert_eq!(reason, "Feb 2019 has no day 29");
+            }
+            _ => panic!("expected InvalidDateError"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_optional_group() -> Result<(), MyCustomError> {
+        let mut source = FormatParser::new("2003-02-01_%0[_%1]");
+        source.parse()?;
+
+        let opt = ParseOpt::default();
+        let mut parser = FileNameParser::new("2021-06-01_acme", source.holes, &opt);
+        parser.parse(&source.nodes)?;
+        assert_eq!(parser.d.holes[&Fragment::Index(0)], "acme");
+        assert_eq!(parser.d.holes[&Fragment::Index(1)], "");
+
+        let opt = ParseOpt::default();
+        let mut parser = FileNameParser::new("2021-06-01_acme_invoice", source.holes, &opt);
+        parser.parse(&source.nodes)?;
+        assert_eq!(parser.d.holes[&Fragment::Index(0)], "acme");
+        assert_eq!(parser.d.holes[&Fragment::Index(1)], "invoice");
+        Ok(())
+    }
+
+    #[test]
+    fn test_alternation_binds_date() -> Result<(), MyCustomError> {
+        let mut source = FormatParser::new("(01.02.2003|2003-02-01)_%0");
+        source.parse()?;
+
+        for s in &["01.06.2021_foo", "2021-06-01_foo"] {
+            let opt = ParseOpt::default();
+            let mut parser = FileNameParser::new(s, source.holes, &opt);
+            parser.parse(&source.nodes)?;
+            assert_eq!(parser.d.year, Some(2021));
+            assert_eq!(parser.d.month, Some(c::Months::Jun));
+            assert_eq!(parser.d.day, Some(1));
+            assert_eq!(parser.d.holes[&Fragment::Index(0)], "foo");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_hole_constraints() -> Result<(), MyCustomError> {
+        let mut source = FormatParser::new("INV%0:d%1:a_%2:{2}%3");
+        source.parse()?;
+
+        let opt = ParseOpt::default();
+        let mut parser = FileNameParser::new("INV12345ACME_DEinvoice", source.holes, &opt);
+        parser.parse(&source.nodes)?;
+        assert_eq!(parser.d.holes[&Fragment::Index(0)], "12345");
+        assert_eq!(parser.d.holes[&Fragment::Index(1)], "ACME");
+        assert_eq!(parser.d.holes[&Fragment::Index(2)], "DE");
+        assert_eq!(parser.d.holes[&Fragment::Index(3)], "invoice");
+        Ok(())
+    }
 }
@@ -1,157 +1,876 @@
 use crate::error::MyCustomError;
-use std::collections::HashSet;
-use log::{debug};
+use crate::strftime::Syntax;
+use convert_case::{Case, Casing};
+use log::debug;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
 
 pub struct FormatParser<'a> {
     pub input: std::iter::Peekable<std::str::Chars<'a>>,
+    pattern: &'a str,
+    pub syntax: Syntax,
+    // The position of the first character of the current identifier or
+    // literal, used to point at it in errors.
+    start: usize,
     pub nodes: Vec<Node>,
     pub keywords: HashSet<char>,
 
     pub holes: usize,
-    sum: u32,
-    count: u32,
+    // The position of the first hole of every index.
+    indexes: HashMap<u32, (usize, usize)>,
+    names: HashSet<String>,
+    // The position of the first week-year, which is only read with a week.
+    pub(crate) iso_year: Option<(usize, usize)>,
+    // The number of alternations the parser is in, where | separates the
+    // alternatives instead of starting a filter.
+    alternations: usize,
+}
+
+// A fragment is referred to either by its index (%0, %12, %{3}) or by its
+// name (%{client}).
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug)]
+pub enum Fragment {
+    Index(u32),
+    Name(String),
+}
+
+impl fmt::Display for Fragment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fragment::Index(i) => write!(f, "%{}", i),
+            Fragment::Name(n) => write!(f, "%{{{}}}", n),
+        }
+    }
+}
+
+// A hole binds the part of a filename to a fragment. The characters of the
+// fragment can be restricted to a class and a fixed width. Filters transform
+// the fragment when it is rendered.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Hole {
+    pub fragment: Fragment,
+    pub class: CharClass,
+    pub width: Option<usize>,
+    pub filters: Vec<Filter>,
+}
+
+impl From<Fragment> for Hole {
+    fn from(fragment: Fragment) -> Self {
+        Hole {
+            fragment,
+            class: CharClass::Any,
+            width: None,
+            filters: Vec::new(),
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum Filter {
+    Lower,
+    Upper,
+    Title,
+    Snake,
+    Kebab,
+    Camel,
+    Pascal,
+    Trim,
+    Replace(String, String),
+    Truncate(usize),
+}
+
+const FILTERS: [&str; 10] = [
+    "lower", "upper", "title", "snake", "kebab", "camel", "pascal", "trim", "replace", "truncate",
+];
+
+impl Filter {
+    pub fn apply(&self, s: &str) -> String {
+        match self {
+            Filter::Lower => s.to_lowercase(),
+            Filter::Upper => s.to_uppercase(),
+            Filter::Title => s.to_case(Case::Title),
+            Filter::Snake => s.to_case(Case::Snake),
+            Filter::Kebab => s.to_case(Case::Kebab),
+            Filter::Camel => s.to_case(Case::Camel),
+            Filter::Pascal => s.to_case(Case::Pascal),
+            Filter::Trim => s.trim().to_string(),
+            Filter::Replace(from, to) => s.replace(from.as_str(), to),
+            Filter::Truncate(n) => s.chars().take(*n).collect(),
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum CharClass {
+    Any,
+    Digits,
+    Letters,
+    // Characters and inclusive ranges of characters, e.g. [A-Z_].
+    Set {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl CharClass {
+    pub fn contains(&self, c: char) -> bool {
+        match self {
+            CharClass::Any => true,
+            CharClass::Digits => c.is_ascii_digit(),
+            CharClass::Letters => c.is_alphabetic(),
+            CharClass::Set { negated, ranges } => {
+                ranges.iter().any(|(a, b)| *a <= c && c <= *b) != *negated
+            }
+        }
+    }
+}
+
+// The values a pattern can bind when it is matched against a filename.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
+pub enum Identifier {
+    Year,
+    Month,
+    Day,
+    Hour,
+    Minute,
+    Second,
+    Weekday,
+    Week,
+    Quarter,
+    DayOfYear,
+    Fragment(Fragment),
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Identifier::Year => write!(f, "year"),
+            Identifier::Month => write!(f, "month"),
+            Identifier::Day => write!(f, "day"),
+            Identifier::Hour => write!(f, "hour"),
+            Identifier::Minute => write!(f, "minute"),
+            Identifier::Second => write!(f, "second"),
+            Identifier::Weekday => write!(f, "weekday"),
+            Identifier::Week => write!(f, "week"),
+            Identifier::Quarter => write!(f, "quarter"),
+            Identifier::DayOfYear => write!(f, "day of the year"),
+            Identifier::Fragment(x) => write!(f, "fragment {}", x),
+        }
+    }
 }
 
 #[derive(PartialEq, Debug)]
 pub enum Node {
-    Hole(u32),
+    Hole(Hole),
     Year,
     YearShort,
     Day,
+    DayUnpadded,
+    DayOrdinal,
     Literal(String),
 
+    // Time of day.
+    Hour,
+    Hour12,
+    Minute,
+    Second,
+    Pm,
+    PmAllCaps,
+
     // Month.
     Feb,
     FebCaps,
     FebAllCaps,
     FebNum,
+    FebNumUnpadded,
     February,
     FebruaryCaps,
     FebruaryAllCaps,
+
+    // Weekday.
+    Sat,
+    SatCaps,
+    SatAllCaps,
+    Saturday,
+    SaturdayCaps,
+    SaturdayAllCaps,
+
+    // ISO 8601 week-year and week, quarter and day of the year.
+    IsoYear,
+    IsoWeek,
+    Quarter,
+    DayOfYear,
+
+    // Seconds and milliseconds since January 1, 1970.
+    EpochSeconds,
+    EpochMillis,
+
+    // Nodes in brackets which may be absent.
+    Optional(Vec<Node>),
+    // Alternatives in parentheses, separated by |.
+    Alternation(Vec<Vec<Node>>),
+    // Sequence number with the given number of digits.
+    Counter(usize),
+}
+
+// Returns the identifiers bound by nodes, including those in groups.
+pub fn identifiers(nodes: &[Node]) -> BTreeSet<Identifier> {
+    let mut ids = BTreeSet::new();
+    for n in nodes {
+        match n {
+            Node::Hole(x) => {
+                ids.insert(Identifier::Fragment(x.fragment.clone()));
+            }
+            Node::Year | Node::YearShort | Node::IsoYear => {
+                ids.insert(Identifier::Year);
+            }
+            Node::IsoWeek => {
+                ids.insert(Identifier::Week);
+            }
+            Node::Quarter => {
+                ids.insert(Identifier::Quarter);
+            }
+            Node::DayOfYear => {
+                ids.insert(Identifier::DayOfYear);
+            }
+            Node::EpochSeconds | Node::EpochMillis => ids.extend(vec![
+                Identifier::Year,
+                Identifier::Month,
+                Identifier::Day,
+                Identifier::Hour,
+                Identifier::Minute,
+                Identifier::Second,
+            ]),
+            Node::Day | Node::DayUnpadded | Node::DayOrdinal => {
+                ids.insert(Identifier::Day);
+            }
+            Node::Feb
+            | Node::FebCaps
+            | Node::FebAllCaps
+            | Node::FebNum
+            | Node::FebNumUnpadded
+            | Node::February
+            | Node::FebruaryCaps
+            | Node::FebruaryAllCaps => {
+                ids.insert(Identifier::Month);
+            }
+            Node::Hour | Node::Hour12 | Node::Pm | Node::PmAllCaps => {
+                ids.insert(Identifier::Hour);
+            }
+            Node::Minute => {
+                ids.insert(Identifier::Minute);
+            }
+            Node::Second => {
+                ids.insert(Identifier::Second);
+            }
+            Node::Sat
+            | Node::SatCaps
+            | Node::SatAllCaps
+            | Node::Saturday
+            | Node::SaturdayCaps
+            | Node::SaturdayAllCaps => {
+                ids.insert(Identifier::Weekday);
+            }
+            Node::Literal(_) | Node::Counter(_) => (),
+            Node::Optional(group) => ids.extend(identifiers(group)),
+            Node::Alternation(branches) => {
+                for b in branches {
+                    ids.extend(identifiers(b));
+                }
+            }
+        }
+    }
+    ids
+}
+
+// Returns the identifiers needed to render nodes. Only the first alternative
+// is rendered, and epoch timestamps need only the date as a missing time counts
+// as midnight.
+pub fn required_identifiers(nodes: &[Node]) -> BTreeSet<Identifier> {
+    let mut ids = BTreeSet::new();
+    for n in nodes {
+        match n {
+            Node::EpochSeconds | Node::EpochMillis => {
+                ids.extend(vec![Identifier::Year, Identifier::Month, Identifier::Day])
+            }
+            Node::Optional(group) => ids.extend(required_identifiers(group)),
+            Node::Alternation(branches) => ids.extend(required_identifiers(&branches[0])),
+            _ => ids.extend(identifiers(std::slice::from_ref(n))),
+        }
+    }
+    ids
+}
+
+// Returns the identifiers which every match of nodes binds. A group which may be
+// absent binds only its fragments, which are left empty, and an alternation
+// binds what all of its alternatives bind. AM/PM only completes an hour and a
+// week-year is only read together with a week.
+fn bound_identifiers(nodes: &[Node]) -> BTreeSet<Identifier> {
+    let mut ids = BTreeSet::new();
+    let mut iso_year = false;
+    for n in nodes {
+        match n {
+            Node::Optional(group) => ids.extend(
+                identifiers(group)
+                    .into_iter()
+                    .filter(|x| matches!(x, Identifier::Fragment(_))),
+            ),
+            Node::Alternation(branches) => {
+                let mut common = bound_identifiers(&branches[0]);
+                for b in &branches[1..] {
+                    let other = bound_identifiers(b);
+                    common.retain(|x| other.contains(x));
+                }
+                ids.extend(common);
+            }
+            Node::Pm | Node::PmAllCaps => (),
+            Node::IsoYear => iso_year = true,
+            _ => ids.extend(identifiers(std::slice::from_ref(n))),
+        }
+    }
+    if iso_year && ids.contains(&Identifier::Week) {
+        ids.insert(Identifier::Year);
+    }
+    ids
+}
+
+// Returns the identifiers known after matching nodes, including those computed
+// from others, e.g. the weekday of a complete date.
+pub fn provided_identifiers(nodes: &[Node]) -> BTreeSet<Identifier> {
+    let mut ids = bound_identifiers(nodes);
+    let has = |ids: &BTreeSet<Identifier>, x: &[Identifier]| x.iter().all(|i| ids.contains(i));
+    if has(&ids, &[Identifier::Year, Identifier::Week])
+        || has(&ids, &[Identifier::Year, Identifier::DayOfYear])
+        || has(&ids, &[Identifier::Quarter])
+    {
+        ids.extend(vec![Identifier::Month, Identifier::Day]);
+    }
+    if has(&ids, &[Identifier::Month]) {
+        ids.insert(Identifier::Quarter);
+    }
+    if has(
+        &ids,
+        &[Identifier::Year, Identifier::Month, Identifier::Day],
+    ) {
+        ids.extend(vec![
+            Identifier::Weekday,
+            Identifier::Week,
+            Identifier::DayOfYear,
+        ]);
+    }
+    ids
+}
+
+// A year in a pattern with a week but without month and day is the week-year.
+fn use_iso_year(nodes: &mut [Node]) {
+    for n in nodes {
+        match n {
+            Node::Year => *n = Node::IsoYear,
+            Node::Optional(group) => use_iso_year(group),
+            Node::Alternation(branches) => {
+                for b in branches {
+                    use_iso_year(b)
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+// Returns the number of insertions, deletions, substitutions and swaps of
+// adjacent characters which turn a into b.
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, x) in d[0].iter_mut().enumerate() {
+        *x = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+// Writes the lowercase s in the case of word: all caps, capitalized or lower.
+fn case_of(word: &str, s: &str) -> String {
+    if !word.chars().any(|c| c.is_lowercase()) {
+        s.to_uppercase()
+    } else if word.starts_with(char::is_uppercase) {
+        s.to_case(Case::Title)
+    } else {
+        s.to_string()
+    }
 }
 
 type Result<T> = std::result::Result<(), T>;
 
 impl FormatParser<'_> {
-    pub fn new(s: &str) -> FormatParser {
+    pub fn new(s: &str) -> FormatParser<'_> {
         let mut kw = HashSet::new();
         kw.insert('%');
         kw.insert('0');
         kw.insert('2');
         kw.insert('F');
         kw.insert('f');
-        return FormatParser {
+        kw.insert('[');
+        kw.insert(']');
+        kw.insert('(');
+        kw.insert(')');
+        kw.insert('|');
+        kw.insert('#');
+        FormatParser {
             input: s.chars().peekable(),
+            pattern: s,
+            syntax: Syntax::Reference,
+            start: 0,
             nodes: Vec::new(),
             keywords: kw,
-            holes: s.matches('%').count(),
-            sum: 0,
-            count: 0,
-        };
+            holes: 0,
+            indexes: HashMap::new(),
+            names: HashSet::new(),
+            iso_year: None,
+            alternations: 0,
+        }
     }
 
     pub fn parse(&mut self) -> Result<MyCustomError> {
-        let res: Result<MyCustomError> = loop {
-            match self.input.peek() {
-                Some(x) if *x == '%' => self.parse_hole()?,
-                Some(x) if *x == '0' => self.parse_date()?,
-                Some(x) if *x == 'F' || *x == 'f' => self.parse_month()?,
-                Some(x) if *x == '2' => self.parse_year_long()?,
-                Some(_) => self.parse_literal()?,
-                None => break Ok(()),
-            };
-        };
-        if self.count > 0 && self.sum != (self.count) * (self.count - 1) / 2 {
-            return Err(MyCustomError::ParseError(
-                "holes have to start at 0 and must not have gaps".to_string(),
-            ));
+        self.parse_nodes(&[])?;
+        // Holes have to start at 0 and must not have gaps. The error points at
+        // the first hole after the gap.
+        if let Some(i) = (0..self.indexes.len() as u32).find(|i| !self.indexes.contains_key(i)) {
+            let (start, end) = self
+                .indexes
+                .iter()
+                .filter(|(j, _)| **j > i)
+                .min_by_key(|(j, _)| **j)
+                .map(|(_, x)| *x)
+                .unwrap();
+            return Err(self.error_at(start, end, format!("missing %{}", i)));
+        }
+        self.holes = self.indexes.len() + self.names.len();
+        let ids = identifiers(&self.nodes);
+        if let Some((start, end)) = self.iso_year {
+            if !ids.contains(&Identifier::Week) {
+                return Err(self.error_at(
+                    start,
+                    end,
+                    "the week-year %G requires the week W%V".to_string(),
+                ));
+            }
+        }
+        if ids.contains(&Identifier::Week)
+            && !ids.contains(&Identifier::Month)
+            && !ids.contains(&Identifier::Day)
+        {
+            use_iso_year(&mut self.nodes);
         }
-        return res;
+        Ok(())
     }
 
-    fn parse_month(&mut self) -> Result<MyCustomError> {
-        let mut is_all_caps = true;
-        let mut is_cap = false;
-        let mut first = true;
-        let mut want = "feb";
-        for c in want.chars() {
-            match self.input.peek() {
-                Some(a) if (*a).to_lowercase().collect::<String>() == c.to_string() => {
-                    if first {
-                        if *a == 'F' {
-                            is_cap = true
-                        }
-                        first = false;
-                    }
-                    if (*a).is_lowercase() {
-                        is_all_caps = false;
-                    }
+    // Returns the position of the next character in characters.
+    fn pos(&self) -> usize {
+        self.pattern.chars().count() - self.input.clone().count()
+    }
+
+    // Returns the start and end of the identifier which was just read.
+    pub(crate) fn span(&self) -> (usize, usize) {
+        (self.start, self.pos())
+    }
+
+    // Returns an error pointing at the pattern from the start of the current
+    // identifier or literal up to the next character.
+    pub(crate) fn error(&self, message: String) -> MyCustomError {
+        self.error_at(self.start, self.pos(), message)
+    }
+
+    pub(crate) fn error_at(&self, start: usize, end: usize, message: String) -> MyCustomError {
+        MyCustomError::PatternError(self.pattern.to_string(), start, end.max(start + 1), message)
+    }
+
+    // Parses the input up to its end or, within a group, up to one of the
+    // closing characters. Returns the closing character that ended the group.
+    fn parse_nodes(
+        &mut self,
+        closing: &[char],
+    ) -> std::result::Result<Option<char>, MyCustomError> {
+        loop {
+            self.start = self.pos();
+            match self.input.peek().copied() {
+                Some('[') => self.parse_optional()?,
+                Some('(') => self.parse_alternation()?,
+                Some('#') => self.parse_counter()?,
+                Some(x) if closing.contains(&x) => {
                     self.input.next();
+                    return Ok(Some(x));
                 }
-                Some(_) => return Err(MyCustomError::ParseError("".to_string())),
-                None => return Err(MyCustomError::ParseError("".to_string())),
-            };
-        }
-        want = "ruary";
-        first = true;
-        for c in want.chars() {
-            match self.input.peek() {
-                Some(a) if (*a).to_lowercase().collect::<String>() == c.to_string() => {
-                    first = false;
-                    if (*a).is_lowercase() {
-                        is_all_caps = false;
-                    }
-                    self.input.next();
+                Some(x) if x == ']' || x == ')' || x == '|' => {
+                    return Err(
+                        self.error(format!("unexpected {}, use \\{} for a literal {}", x, x, x))
+                    )
                 }
-                Some(_) => {
-                    if first {
-                        if is_all_caps {
-                            self.nodes.push(Node::FebAllCaps);
-                        } else if is_cap {
-                            self.nodes.push(Node::FebCaps);
-                        } else {
-                            self.nodes.push(Node::Feb);
-                        };
-                        return Ok(());
-                    } else {
-                        return Err(MyCustomError::ParseError("".to_string()));
-                    };
+                Some(_) if self.syntax == Syntax::Strftime => self.parse_directive()?,
+                Some(_) => self.parse_identifier()?,
+                None if closing.is_empty() => return Ok(None),
+                None => {
+                    return Err(self.error(format!(
+                        "group is missing the closing {}",
+                        closing.last().unwrap()
+                    )))
                 }
-                None => return Err(MyCustomError::ParseError("".to_string())),
             };
         }
-        if is_all_caps {
-            self.nodes.push(Node::FebruaryAllCaps);
-        } else if is_cap {
-            self.nodes.push(Node::FebruaryCaps);
-        } else {
-            self.nodes.push(Node::February);
+    }
+
+    // Parses an identifier or a literal written in the reference syntax.
+    fn parse_identifier(&mut self) -> Result<MyCustomError> {
+        match self.input.peek().copied() {
+            Some('%') => self.parse_hole(),
+            Some('0') if self.lookahead("032") => self.parse_day_of_year(),
+            Some('0') => self.parse_date(),
+            Some('W') if self.at_prefixed("W05") => self.parse_week(),
+            Some('Q') if self.at_prefixed("Q1") => self.parse_quarter(),
+            Some('F') | Some('f') => self.parse_month(),
+            Some('1') if self.lookahead("1044122645") => self.parse_epoch(),
+            Some('2') if self.lookahead("2003") => self.parse_year_long(),
+            Some('2') if self.stands_apart(1, true) => self.parse_unpadded(Node::FebNumUnpadded),
+            Some(_) if self.at_hour() => self.parse_hour(),
+            Some(_) if self.lookahead("1st") && self.stands_apart(3, true) => self.parse_ordinal(),
+            Some('1') if self.stands_apart(1, true) => self.parse_unpadded(Node::DayUnpadded),
+            Some(_) if self.at_pm() => self.parse_pm(),
+            Some(_) if self.at_weekday() => self.parse_weekday(),
+            _ => self.parse_literal(),
+        }
+    }
+
+    // Parses the nodes of a group into a separate list.
+    fn parse_group(
+        &mut self,
+        closing: &[char],
+    ) -> std::result::Result<(Vec<Node>, Option<char>), MyCustomError> {
+        let outer = std::mem::take(&mut self.nodes);
+        let res = self.parse_nodes(closing);
+        let group = std::mem::replace(&mut self.nodes, outer);
+        Ok((group, res?))
+    }
+
+    fn parse_optional(&mut self) -> Result<MyCustomError> {
+        debug!("parse_optional");
+        let start = self.start;
+        self.input.next();
+        let (group, _) = self.parse_group(&[']'])?;
+        if group.is_empty() {
+            return Err(self.error_at(
+                start,
+                self.pos(),
+                "optional group must not be empty".to_string(),
+            ));
+        }
+        self.nodes.push(Node::Optional(group));
+        Ok(())
+    }
+
+    fn parse_alternation(&mut self) -> Result<MyCustomError> {
+        debug!("parse_alternation");
+        let start = self.start;
+        self.input.next();
+        let mut branches = Vec::new();
+        self.alternations += 1;
+        loop {
+            let (branch, closing) = self.parse_group(&['|', ')'])?;
+            branches.push(branch);
+            if closing == Some(')') {
+                break;
+            }
+        }
+        self.alternations -= 1;
+        let want = identifiers(&branches[0]);
+        for b in &branches[1..] {
+            let got = identifiers(b);
+            if let Some(x) = want.symmetric_difference(&got).next() {
+                return Err(self.error_at(start, self.pos(), format!(
+                    "all alternatives have to contain the same identifiers, but {} is not part of every alternative",
+                    x
+                )));
+            }
+        }
+        self.nodes.push(Node::Alternation(branches));
+        Ok(())
+    }
+
+    fn parse_counter(&mut self) -> Result<MyCustomError> {
+        debug!("parse_counter");
+        let mut width = 0;
+        while self.input.peek() == Some(&'#') {
+            self.input.next();
+            width += 1;
+        }
+        self.nodes.push(Node::Counter(width));
+        Ok(())
+    }
+
+    // Returns true if the remaining input starts with s.
+    pub(crate) fn lookahead(&self, s: &str) -> bool {
+        let mut input = self.input.clone();
+        s.chars().all(|c| input.next() == Some(c))
+    }
+
+    fn lookahead_ignore_case(&self, s: &str) -> bool {
+        let mut input = self.input.clone();
+        s.chars()
+            .all(|c| input.next().map(|x| x.to_ascii_lowercase()) == Some(c))
+    }
+
+    // Returns true if the len characters at the current position are not part
+    // of a longer word, i.e. neither the character before nor the one after
+    // them is a letter, or a digit if digits is set. This keeps words like
+    // compensation literal instead of reading a weekday in them.
+    fn stands_apart(&self, len: usize, digits: bool) -> bool {
+        let pos = self.pos();
+        let before = match pos {
+            0 => None,
+            _ => self.pattern.chars().nth(pos - 1),
+        };
+        let after = self.input.clone().nth(len);
+        let touches = |c: Option<char>| {
+            c.is_some_and(|c| c.is_alphabetic() || (digits && c.is_ascii_digit()))
         };
-        return Ok(());
+        !touches(before) && !touches(after)
+    }
+
+    // A week or quarter may follow the year directly, like in 2003W05, but
+    // must not be followed by further digits.
+    fn at_prefixed(&self, s: &str) -> bool {
+        let len = s.chars().count();
+        self.lookahead(s)
+            && self.stands_apart(len, false)
+            && !self
+                .input
+                .clone()
+                .nth(len)
+                .is_some_and(|c| c.is_ascii_digit())
+    }
+
+    // The hour may be followed by minutes and seconds, like in 180405.
+    fn at_hour(&self) -> bool {
+        self.lookahead("18") && self.stands_apart(2, false)
+    }
+
+    fn at_pm(&self) -> bool {
+        (self.lookahead("PM") || self.lookahead("pm")) && self.stands_apart(2, false)
+    }
+
+    fn at_weekday(&self) -> bool {
+        (self.lookahead_ignore_case("saturday") && self.stands_apart(8, false))
+            || (self.lookahead_ignore_case("sat") && self.stands_apart(3, false))
+    }
+
+    // Returns true if the next character starts an identifier.
+    fn at_identifier(&mut self) -> bool {
+        if self.syntax == Syntax::Strftime {
+            return self.at_directive();
+        }
+        match self.input.peek().copied() {
+            // A day or month without leading zero next to letters or digits,
+            // like the 1 in v1, is a literal.
+            Some('1') | Some('2') => {
+                self.lookahead("1044122645")
+                    || self.lookahead("2003")
+                    || self.at_hour()
+                    || (self.lookahead("1st") && self.stands_apart(3, true))
+                    || self.stands_apart(1, true)
+            }
+            Some(x) if self.keywords.contains(&x) => true,
+            Some(_) => {
+                self.at_prefixed("W05")
+                    || self.at_prefixed("Q1")
+                    || self.at_pm()
+                    || self.at_weekday()
+            }
+            None => false,
+        }
+    }
+
+    fn parse_weekday(&mut self) -> Result<MyCustomError> {
+        debug!("parse_weekday");
+        let mut weekday: String = self.input.by_ref().take(3).collect();
+        let long = self.lookahead_ignore_case("urday");
+        if long {
+            weekday.extend(self.input.by_ref().take(5));
+        }
+        let is_all_caps = !weekday.chars().any(|x| x.is_lowercase());
+        let is_cap = weekday.starts_with('S');
+        self.nodes.push(match (long, is_all_caps, is_cap) {
+            (false, true, _) => Node::SatAllCaps,
+            (false, false, true) => Node::SatCaps,
+            (false, false, false) => Node::Sat,
+            (true, true, _) => Node::SaturdayAllCaps,
+            (true, false, true) => Node::SaturdayCaps,
+            (true, false, false) => Node::Saturday,
+        });
+        Ok(())
+    }
+
+    fn parse_hour(&mut self) -> Result<MyCustomError> {
+        debug!("parse_hour");
+        self.input.nth(1);
+        self.nodes.push(Node::Hour);
+        Ok(())
+    }
+
+    fn parse_pm(&mut self) -> Result<MyCustomError> {
+        debug!("parse_pm");
+        match self.input.next() {
+            Some('P') => self.nodes.push(Node::PmAllCaps),
+            _ => self.nodes.push(Node::Pm),
+        }
+        self.input.next();
+        Ok(())
+    }
+
+    // Parses feb or february in the case of the pattern. A word which is close
+    // to one of them but not quite, such as Febuary, is an error rather than a
+    // month followed by a literal.
+    fn parse_month(&mut self) -> Result<MyCustomError> {
+        debug!("parse_month");
+        let word: String = self
+            .input
+            .clone()
+            .take_while(|c| c.is_alphabetic())
+            .collect();
+        let lower = word.to_lowercase();
+        let long = lower.starts_with("february");
+        let short = lower.starts_with("feb") && !lower.starts_with("febr");
+        if !(long || short) || (short && lower.len() > 3 && distance(&lower, "february") <= 2) {
+            return Err(self.month_error(&word));
+        }
+        let month: String = self.input.by_ref().take(if long { 8 } else { 3 }).collect();
+        let is_all_caps = !month.chars().any(|x| x.is_lowercase());
+        let is_cap = month.starts_with('F');
+        self.nodes.push(match (long, is_all_caps, is_cap) {
+            (false, true, _) => Node::FebAllCaps,
+            (false, false, true) => Node::FebCaps,
+            (false, false, false) => Node::Feb,
+            (true, true, _) => Node::FebruaryAllCaps,
+            (true, false, true) => Node::FebruaryCaps,
+            (true, false, false) => Node::February,
+        });
+        Ok(())
+    }
+
+    // Suggests the closest spelling of the month if the word is a near miss
+    // and lists the accepted spellings otherwise.
+    fn month_error(&self, word: &str) -> MyCustomError {
+        let lower = word.to_lowercase();
+        let end = self.start + word.chars().count();
+        let suggestion = ["feb", "february"]
+            .iter()
+            .map(|m| (distance(&lower, m), *m))
+            .filter(|(d, m)| *d <= m.len() / 3)
+            .min();
+        if let Some((_, m)) = suggestion {
+            return self.error_at(
+                self.start,
+                end,
+                format!("unknown month {}, did you mean {}?", word, case_of(word, m)),
+            );
+        }
+        let f = word.chars().next().unwrap_or('f');
+        self.error_at(
+            self.start,
+            end,
+            format!(
+                "after `{}` expected `{}` or `{}`, use \\{} for a literal {}",
+                f,
+                case_of(word, "feb"),
+                case_of(word, "february"),
+                f,
+                f
+            ),
+        )
     }
 
     fn parse_date(&mut self) -> Result<MyCustomError> {
         debug!("parse_date");
         self.input.next();
-        match self.input.peek() {
-            Some(a) if *a == '3' => {
-                self.nodes.push(Node::YearShort);
-                self.input.next();
-                return Ok(());
-            }
-            Some(a) if *a == '2' => {
-                self.input.next();
-                self.nodes.push(Node::FebNum);
-                return Ok(());
+        match self.input.peek().copied() {
+            Some('3') => self.nodes.push(Node::YearShort),
+            Some('2') => self.nodes.push(Node::FebNum),
+            Some('1') => self.nodes.push(Node::Day),
+            Some('6') => self.nodes.push(Node::Hour12),
+            Some('4') => self.nodes.push(Node::Minute),
+            Some('5') => self.nodes.push(Node::Second),
+            next => {
+                let end = self.pos() + next.map_or(0, |_| 1);
+                return Err(self.error_at(
+                    self.start,
+                    end,
+                    "after `0` expected `1`, `2`, `3`, `4`, `5`, `6` or `32`, use \\0 for a literal 0"
+                        .to_string(),
+                ));
             }
-            Some(a) if *a == '1' => {
-                self.input.next();
-                self.nodes.push(Node::Day);
-                return Ok(());
-            }
-            Some(_) => Err(MyCustomError::ParseError("".to_string())),
-            None => Err(MyCustomError::ParseError("".to_string())),
         }
+        self.input.next();
+        Ok(())
+    }
+
+    // Parses the single digit 1 or 2 of a day or month without leading zero.
+    fn parse_unpadded(&mut self, node: Node) -> Result<MyCustomError> {
+        debug!("parse_unpadded");
+        self.input.next();
+        self.nodes.push(node);
+        Ok(())
+    }
+
+    fn parse_ordinal(&mut self) -> Result<MyCustomError> {
+        debug!("parse_ordinal");
+        self.input.nth(2);
+        self.nodes.push(Node::DayOrdinal);
+        Ok(())
+    }
+
+    fn parse_epoch(&mut self) -> Result<MyCustomError> {
+        debug!("parse_epoch");
+        self.input.nth(9);
+        if self.lookahead("000") {
+            self.input.nth(2);
+            self.nodes.push(Node::EpochMillis);
+        } else {
+            self.nodes.push(Node::EpochSeconds);
+        }
+        Ok(())
+    }
+
+    fn parse_day_of_year(&mut self) -> Result<MyCustomError> {
+        debug!("parse_day_of_year");
+        self.input.nth(2);
+        self.nodes.push(Node::DayOfYear);
+        Ok(())
+    }
+
+    fn parse_week(&mut self) -> Result<MyCustomError> {
+        debug!("parse_week");
+        self.input.nth(2);
+        self.nodes.push(Node::IsoWeek);
+        Ok(())
+    }
+
+    fn parse_quarter(&mut self) -> Result<MyCustomError> {
+        debug!("parse_quarter");
+        self.input.nth(1);
+        self.nodes.push(Node::Quarter);
+        Ok(())
     }
 
     fn parse_year_long(&mut self) -> Result<MyCustomError> {
@@ -163,70 +882,342 @@ impl FormatParser<'_> {
                     self.input.next();
                     Ok(())
                 }
-                Some(_) => Err(MyCustomError::ParseError("invalid long year".to_string())),
-                None => Err(MyCustomError::ParseError(
-                    "invalid long year. The format ended too early".to_string(),
-                )),
+                Some(_) => Err(self.error("invalid long year".to_string())),
+                None => {
+                    Err(self.error("invalid long year. The format ended too early".to_string()))
+                }
             }?;
         }
         self.nodes.push(Node::Year);
-        return Ok(());
+        Ok(())
     }
 
     fn parse_hole(&mut self) -> Result<MyCustomError> {
         debug!("parse hole");
         self.input.next();
-        match self.input.peek() {
-            Some(x) => match x.to_digit(10) {
-                Some(x) => {
-                    self.sum += x;
-                    self.count += 1;
-                    self.nodes.push(Node::Hole(x));
-                    self.input.next();
-                    return Ok(());
+        self.parse_fragment()
+    }
+
+    // Parses the index or name of a hole in braces or the digits of an index,
+    // followed by its constraint and filters.
+    pub(crate) fn parse_fragment(&mut self) -> Result<MyCustomError> {
+        let fragment = match self.input.peek().copied() {
+            Some('{') => {
+                self.input.next();
+                let mut name = String::from("");
+                loop {
+                    match self.input.next() {
+                        Some('}') => break,
+                        Some(x) if x.is_alphanumeric() || x == '_' || x == '-' => name.push(x),
+                        Some(x) => {
+                            return Err(self.error(format!(
+                                "could not parse hole, {} is not allowed in a name",
+                                x
+                            )))
+                        }
+                        None => {
+                            return Err(self.error("could not parse hole, missing }".to_string()))
+                        }
+                    }
                 }
-                None => {
-                    return Err(MyCustomError::ParseError(format!(
-                        "could not parse hole, {} is not a digit",
-                        x
-                    )))
+                match name.parse::<u32>() {
+                    Ok(i) => Fragment::Index(i),
+                    Err(_) if name.is_empty() => {
+                        return Err(
+                            self.error("could not parse hole, name must not be empty".to_string())
+                        )
+                    }
+                    Err(_) => Fragment::Name(name),
                 }
-            },
-            None => {
-                return Err(MyCustomError::ParseError(
-                    "next() did non return a character".to_string(),
+            }
+            Some(x) if x.is_ascii_digit() => {
+                let mut i: u32 = 0;
+                while let Some(d) = self.input.peek().and_then(|x| x.to_digit(10)) {
+                    i = i
+                        .checked_mul(10)
+                        .and_then(|i| i.checked_add(d))
+                        .ok_or_else(|| self.error("hole index is too large".to_string()))?;
+                    self.input.next();
+                }
+                Fragment::Index(i)
+            }
+            _ => {
+                return Err(self.error(
+                    "after `%` expected a digit or `{`, use \\% for a literal %".to_string(),
                 ))
             }
         };
+        match &fragment {
+            Fragment::Index(i) => {
+                let span = self.span();
+                self.indexes.entry(*i).or_insert(span);
+            }
+            Fragment::Name(n) => {
+                self.names.insert(n.clone());
+            }
+        };
+        let mut hole = Hole::from(fragment);
+        if self.input.peek() == Some(&':') && self.at_constraint() {
+            self.input.next();
+            self.parse_constraint(&mut hole)?;
+        }
+        while self.input.peek() == Some(&'|') && self.at_filter() {
+            self.input.next();
+            hole.filters.push(self.parse_filter()?);
+        }
+        self.nodes.push(Node::Hole(hole));
+        Ok(())
     }
 
-    fn parse_literal(&mut self) -> Result<MyCustomError> {
+    // Returns true if the | at the start of the input is followed by the name
+    // of a filter outside of alternatives. Otherwise it separates alternatives.
+    fn at_filter(&self) -> bool {
+        if self.alternations > 0 {
+            return false;
+        }
+        let mut input = self.input.clone();
+        input.next();
+        let name: String = input.take_while(|x| x.is_ascii_alphabetic()).collect();
+        FILTERS.contains(&&name[..])
+    }
 
-    debug!("parsing literal");
-        let mut literal = String::from("");
+    fn parse_filter(&mut self) -> std::result::Result<Filter, MyCustomError> {
+        debug!("parse_filter");
+        let mut name = String::from("");
+        while let Some(x) = self.input.peek() {
+            if !x.is_ascii_alphabetic() {
+                break;
+            }
+            name.push(*x);
+            self.input.next();
+        }
+        let args = if self.input.peek() == Some(&'(') {
+            self.input.next();
+            self.parse_filter_args()?
+        } else {
+            Vec::new()
+        };
+        let filter = match (&name[..], &args[..]) {
+            ("lower", []) => Filter::Lower,
+            ("upper", []) => Filter::Upper,
+            ("title", []) => Filter::Title,
+            ("snake", []) => Filter::Snake,
+            ("kebab", []) => Filter::Kebab,
+            ("camel", []) => Filter::Camel,
+            ("pascal", []) => Filter::Pascal,
+            ("trim", []) => Filter::Trim,
+            ("replace", [from, to]) if !from.is_empty() => {
+                Filter::Replace(from.to_string(), to.to_string())
+            }
+            ("truncate", [n]) => match n.parse() {
+                Ok(n) => Filter::Truncate(n),
+                Err(_) => return Err(self.error(format!("truncate expects a number, got {}", n))),
+            },
+            ("replace", _) => {
+                return Err(self
+                    .error("replace expects two arguments, e.g. replace(\" \",\"-\")".to_string()))
+            }
+            ("truncate", _) => {
+                return Err(
+                    self.error("truncate expects one argument, e.g. truncate(20)".to_string())
+                )
+            }
+            (name, _) => return Err(self.error(format!("filter {} does not take arguments", name))),
+        };
+        Ok(filter)
+    }
+
+    fn missing_args(&self) -> MyCustomError {
+        self.error("arguments of filter are missing the closing )".to_string())
+    }
+
+    // Parses comma separated arguments up to the closing parenthesis. Arguments
+    // can be quoted with double quotes, in which case a backslash escapes the
+    // following character.
+    fn parse_filter_args(&mut self) -> std::result::Result<Vec<String>, MyCustomError> {
+        let mut args = Vec::new();
         loop {
-            match self.input.peek() {
-                Some(x) if self.keywords.contains(x) => break,
-                Some(x) => {
-                    literal.push(*x);
+            let mut arg = String::from("");
+            while self.input.peek() == Some(&' ') {
+                self.input.next();
+            }
+            if self.input.peek() == Some(&'"') {
+                self.input.next();
+                loop {
+                    match self.input.next() {
+                        Some('"') => break,
+                        Some('\\') => match self.input.next() {
+                            Some(x) => arg.push(x),
+                            None => return Err(self.missing_args()),
+                        },
+                        Some(x) => arg.push(x),
+                        None => return Err(self.missing_args()),
+                    }
+                }
+                while self.input.peek() == Some(&' ') {
+                    self.input.next();
+                }
+            } else {
+                while let Some(x) = self.input.peek() {
+                    if *x == ',' || *x == ')' {
+                        break;
+                    }
+                    arg.push(*x);
                     self.input.next();
                 }
+                arg = arg.trim_end().to_string();
+            }
+            args.push(arg);
+            match self.input.next() {
+                Some(',') => (),
+                Some(')') => break,
+                _ => return Err(self.missing_args()),
+            }
+        }
+        if args == [""] {
+            args.clear();
+        }
+        Ok(args)
+    }
+
+    // Returns true if the colon at the start of the input introduces a
+    // constraint of a hole, e.g. %0:d, %0:a, %0:[A-Z] or %0:{4}.
+    fn at_constraint(&self) -> bool {
+        let mut input = self.input.clone();
+        input.next();
+        matches!(input.next(), Some('d') | Some('a') | Some('[') | Some('{'))
+    }
+
+    fn parse_constraint(&mut self, hole: &mut Hole) -> Result<MyCustomError> {
+        debug!("parse_constraint");
+        match self.input.peek() {
+            Some('d') => {
+                self.input.next();
+                hole.class = CharClass::Digits;
+            }
+            Some('a') => {
+                self.input.next();
+                hole.class = CharClass::Letters;
+            }
+            Some('[') => {
+                self.input.next();
+                hole.class = self.parse_char_class()?;
+            }
+            _ => (),
+        }
+        if self.input.peek() == Some(&'{') {
+            self.input.next();
+            let mut width = String::from("");
+            loop {
+                match self.input.next() {
+                    Some('}') => break,
+                    Some(x) if x.is_ascii_digit() => width.push(x),
+                    _ => {
+                        return Err(self.error(
+                            "width of a hole has to be a number in braces, e.g. {4}".to_string(),
+                        ))
+                    }
+                }
+            }
+            match width.parse::<usize>() {
+                Ok(w) if w > 0 => hole.width = Some(w),
+                _ => return Err(self.error("width of a hole has to be at least 1".to_string())),
+            }
+        }
+        Ok(())
+    }
+
+    // Parses the characters of a class up to the closing bracket. A leading ^
+    // negates the class and a backslash escapes the following character.
+    fn parse_char_class(&mut self) -> std::result::Result<CharClass, MyCustomError> {
+        let negated = self.input.peek() == Some(&'^');
+        if negated {
+            self.input.next();
+        }
+        let mut chars = Vec::new();
+        loop {
+            match self.input.next() {
+                Some(']') => break,
+                Some('\\') => match self.input.next() {
+                    Some(x) => chars.push((x, true)),
+                    None => break,
+                },
+                Some(x) => chars.push((x, false)),
+                None => {
+                    return Err(self.error("character class is missing the closing ]".to_string()))
+                }
+            }
+        }
+        let mut ranges = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let (a, _) = chars[i];
+            match (chars.get(i + 1), chars.get(i + 2)) {
+                (Some(('-', false)), Some((b, _))) => {
+                    if *b < a {
+                        return Err(
+                            self.error(format!("invalid range {}-{} in character class", a, b))
+                        );
+                    }
+                    ranges.push((a, *b));
+                    i += 3;
+                }
+                _ => {
+                    ranges.push((a, a));
+                    i += 1;
+                }
+            }
+        }
+        if ranges.is_empty() {
+            return Err(self.error("character class must not be empty".to_string()));
+        }
+        Ok(CharClass::Set { negated, ranges })
+    }
+
+    // A backslash escapes the following character, so that keywords can be
+    // part of a literal.
+    pub(crate) fn parse_literal(&mut self) -> Result<MyCustomError> {
+        debug!("parsing literal");
+        let mut literal = String::from("");
+        while !self.at_identifier() {
+            match self.input.next() {
+                Some('\\') => match self.input.next() {
+                    Some(x) => literal.push(x),
+                    None => {
+                        return Err(
+                            self.error("pattern must not end with an escape character".to_string())
+                        )
+                    }
+                },
+                Some(x) => literal.push(x),
                 None => break,
             }
         }
         debug!("literal: {}", literal);
-        self.nodes.push(Node::Literal(literal));
-        return Ok(());
+        self.push_literal(&literal);
+        Ok(())
+    }
+
+    // Appends to the previous node if it is a literal as well.
+    pub(crate) fn push_literal(&mut self, s: &str) {
+        match self.nodes.last_mut() {
+            Some(Node::Literal(x)) => x.push_str(s),
+            _ => self.nodes.push(Node::Literal(s.to_string())),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::format_parser::{FormatParser, Node};
+    use crate::error::MyCustomError;
+    use crate::format_parser::{
+        provided_identifiers, required_identifiers, CharClass, Filter, FormatParser, Fragment,
+        Hole, Identifier, Node,
+    };
     #[test]
     fn test_year_long() {
         let format_string = String::from("2003");
-        let want = vec![Node::Year];
+        let want = [Node::Year];
         let mut p = FormatParser::new(&format_string);
         assert!(p.parse().is_ok());
         for (i, n) in want.iter().enumerate() {
@@ -236,7 +1227,7 @@ mod tests {
     #[test]
     fn test_year_short() {
         let format_string = String::from("03");
-        let want = vec![Node::YearShort];
+        let want = [Node::YearShort];
         let mut p = FormatParser::new(&format_string);
         assert!(p.parse().is_ok());
         for (i, n) in want.iter().enumerate() {
@@ -246,7 +1237,7 @@ mod tests {
     #[test]
     fn test_mixed_1() {
         let format_string = String::from("030201");
-        let want = vec![Node::YearShort, Node::FebNum, Node::Day];
+        let want = [Node::YearShort, Node::FebNum, Node::Day];
         let mut p = FormatParser::new(&format_string);
         assert!(p.parse().is_ok());
         for (i, n) in want.iter().enumerate() {
@@ -256,7 +1247,7 @@ mod tests {
     #[test]
     fn test_long_year_with_literals() {
         let format_string = String::from("2003-02-01");
-        let want = vec![
+        let want = [
             Node::Year,
             Node::Literal("-".to_string()),
             Node::FebNum,
@@ -272,7 +1263,7 @@ mod tests {
     #[test]
     fn test_mixes_month() {
         let format_string = String::from("Februaryfebfebruary");
-        let want = vec![Node::FebruaryCaps, Node::Feb, Node::February];
+        let want = [Node::FebruaryCaps, Node::Feb, Node::February];
         let mut p = FormatParser::new(&format_string);
         assert!(p.parse().is_ok());
         for (i, n) in want.iter().enumerate() {
@@ -282,7 +1273,7 @@ mod tests {
     #[test]
     fn test_year_feb_day() {
         let format_string = String::from("2003-FEB-01");
-        let want = vec![
+        let want = [
             Node::Year,
             Node::Literal("-".to_string()),
             Node::FebAllCaps,
@@ -298,7 +1289,7 @@ mod tests {
     #[test]
     fn test_year_february_day() {
         let format_string = String::from("2003-february-01");
-        let want = vec![
+        let want = [
             Node::Year,
             Node::Literal("-".to_string()),
             Node::February,
@@ -312,9 +1303,515 @@ mod tests {
         }
     }
     #[test]
+    fn test_weekday() {
+        let format_string = String::from("SAT_Saturday_sat-SATURDAY_Sat_saturday");
+        let want = [
+            Node::SatAllCaps,
+            Node::Literal("_".to_string()),
+            Node::SaturdayCaps,
+            Node::Literal("_".to_string()),
+            Node::Sat,
+            Node::Literal("-".to_string()),
+            Node::SaturdayAllCaps,
+            Node::Literal("_".to_string()),
+            Node::SatCaps,
+            Node::Literal("_".to_string()),
+            Node::Saturday,
+        ];
+        let mut p = FormatParser::new(&format_string);
+        assert!(p.parse().is_ok());
+        assert_eq!(p.nodes.len(), want.len());
+        for (i, n) in want.iter().enumerate() {
+            assert_eq!(&p.nodes[i], n)
+        }
+    }
+    #[test]
+    fn test_escape() {
+        let format_string = String::from(r"\2\FA_\%\0_\final_v\2\\_%0");
+        let want = [
+            Node::Literal("2FA_%0_final_v2\\_".to_string()),
+            Node::Hole(Fragment::Index(0).into()),
+        ];
+        let mut p = FormatParser::new(&format_string);
+        assert!(p.parse().is_ok());
+        assert_eq!(p.nodes.len(), want.len());
+        for (i, n) in want.iter().enumerate() {
+            assert_eq!(&p.nodes[i], n)
+        }
+        assert_eq!(p.holes, 1);
+    }
+    #[test]
+    fn test_escape_at_end() {
+        let format_string = String::from(r"%0\");
+        let mut p = FormatParser::new(&format_string);
+        assert!(p.parse().is_err());
+    }
+    #[test]
+    fn test_holes() {
+        let format_string = String::from("%{client}_%1%{0}_%{title}");
+        let want = [
+            Node::Hole(Fragment::Name("client".to_string()).into()),
+            Node::Literal("_".to_string()),
+            Node::Hole(Fragment::Index(1).into()),
+            Node::Hole(Fragment::Index(0).into()),
+            Node::Literal("_".to_string()),
+            Node::Hole(Fragment::Name("title".to_string()).into()),
+        ];
+        let mut p = FormatParser::new(&format_string);
+        assert!(p.parse().is_ok());
+        assert_eq!(p.nodes.len(), want.len());
+        for (i, n) in want.iter().enumerate() {
+            assert_eq!(&p.nodes[i], n)
+        }
+    }
+    #[test]
+    fn test_hole_count() {
+        let mut p = FormatParser::new("%1_%{name}_%0_%1_%2_%3_%4_%5_%6_%7_%8_%9_%10");
+        assert!(p.parse().is_ok());
+        assert_eq!(p.holes, 12);
+    }
+    #[test]
+    fn test_invalid_named_hole() {
+        for format_string in &["%{}", "%{a b}", "%{abc", "%1_%{a}"] {
+            let mut p = FormatParser::new(format_string);
+            assert!(p.parse().is_err());
+        }
+    }
+    #[test]
+    fn test_optional() {
+        let format_string = String::from("2003[_%0[-%1]]");
+        let want = [
+            Node::Year,
+            Node::Optional(vec![
+                Node::Literal("_".to_string()),
+                Node::Hole(Fragment::Index(0).into()),
+                Node::Optional(vec![
+                    Node::Literal("-".to_string()),
+                    Node::Hole(Fragment::Index(1).into()),
+                ]),
+            ]),
+        ];
+        let mut p = FormatParser::new(&format_string);
+        assert!(p.parse().is_ok());
+        assert_eq!(p.nodes.len(), want.len());
+        for (i, n) in want.iter().enumerate() {
+            assert_eq!(&p.nodes[i], n)
+        }
+    }
+    #[test]
+    fn test_invalid_optional() {
+        for format_string in &["[%0", "%0]", "%0[]", "[%0[_%1]"] {
+            let mut p = FormatParser::new(format_string);
+            assert!(p.parse().is_err());
+        }
+    }
+    #[test]
+    fn test_alternation() {
+        let format_string = String::from("(01.02.2003|2003-02-01)_%0");
+        let want = [
+            Node::Alternation(vec![
+                vec![
+                    Node::Day,
+                    Node::Literal(".".to_string()),
+                    Node::FebNum,
+                    Node::Literal(".".to_string()),
+                    Node::Year,
+                ],
+                vec![
+                    Node::Year,
+                    Node::Literal("-".to_string()),
+                    Node::FebNum,
+                    Node::Literal("-".to_string()),
+                    Node::Day,
+                ],
+            ]),
+            Node::Literal("_".to_string()),
+            Node::Hole(Fragment::Index(0).into()),
+        ];
+        let mut p = FormatParser::new(&format_string);
+        assert!(p.parse().is_ok());
+        assert_eq!(p.nodes.len(), want.len());
+        for (i, n) in want.iter().enumerate() {
+            assert_eq!(&p.nodes[i], n)
+        }
+    }
+    #[test]
+    fn test_invalid_alternation() {
+        for format_string in &[
+            "(2003|%0)",
+            "(2003|02)",
+            "(2003-02",
+            "2003)",
+            "2003|02",
+            "(%0|%1)",
+            "(%0|title)",
+        ] {
+            let mut p = FormatParser::new(format_string);
+            assert!(p.parse().is_err());
+        }
+    }
+    #[test]
+    fn test_hole_constraints() {
+        let format_string = String::from("INV%0:d%1:a_%2:{4}%3:[^A-Z_0-9]{2}_%4:d");
+        let want = [
+            Node::Literal("INV".to_string()),
+            Node::Hole(Hole {
+                fragment: Fragment::Index(0),
+                class: CharClass::Digits,
+                width: None,
+                filters: vec![],
+            }),
+            Node::Hole(Hole {
+                fragment: Fragment::Index(1),
+                class: CharClass::Letters,
+                width: None,
+                filters: vec![],
+            }),
+            Node::Literal("_".to_string()),
+            Node::Hole(Hole {
+                fragment: Fragment::Index(2),
+                class: CharClass::Any,
+                width: Some(4),
+                filters: vec![],
+            }),
+            Node::Hole(Hole {
+                fragment: Fragment::Index(3),
+                class: CharClass::Set {
+                    negated: true,
+                    ranges: vec![('A', 'Z'), ('_', '_'), ('0', '9')],
+                },
+                width: Some(2),
+                filters: vec![],
+            }),
+            Node::Literal("_".to_string()),
+            Node::Hole(Hole {
+                fragment: Fragment::Index(4),
+                class: CharClass::Digits,
+                width: None,
+                filters: vec![],
+            }),
+        ];
+        let mut p = FormatParser::new(&format_string);
+        assert!(p.parse().is_ok());
+        assert_eq!(p.nodes.len(), want.len());
+        for (i, n) in want.iter().enumerate() {
+            assert_eq!(&p.nodes[i], n)
+        }
+    }
+    #[test]
+    fn test_colon_after_hole() {
+        let format_string = String::from("%0:%1");
+        let want = [
+            Node::Hole(Fragment::Index(0).into()),
+            Node::Literal(":".to_string()),
+            Node::Hole(Fragment::Index(1).into()),
+        ];
+        let mut p = FormatParser::new(&format_string);
+        assert!(p.parse().is_ok());
+        assert_eq!(p.nodes.len(), want.len());
+        for (i, n) in want.iter().enumerate() {
+            assert_eq!(&p.nodes[i], n)
+        }
+    }
+    #[test]
+    fn test_invalid_hole_constraints() {
+        for format_string in &["%0:{0}", "%0:{x}", "%0:[]", "%0:[z-a]", "%0:[a-z"] {
+            let mut p = FormatParser::new(format_string);
+            assert!(p.parse().is_err());
+        }
+    }
+    #[test]
+    fn test_filters() {
+        let format_string =
+            String::from(r#"%0|trim|replace(" ", "-")|upper|truncate(20)_%1|snake"#);
+        let want = [
+            Node::Hole(Hole {
+                fragment: Fragment::Index(0),
+                class: CharClass::Any,
+                width: None,
+                filters: vec![
+                    Filter::Trim,
+                    Filter::Replace(" ".to_string(), "-".to_string()),
+                    Filter::Upper,
+                    Filter::Truncate(20),
+                ],
+            }),
+            Node::Literal("_".to_string()),
+            Node::Hole(Hole {
+                fragment: Fragment::Index(1),
+                class: CharClass::Any,
+                width: None,
+                filters: vec![Filter::Snake],
+            }),
+        ];
+        let mut p = FormatParser::new(&format_string);
+        assert!(p.parse().is_ok());
+        assert_eq!(p.nodes.len(), want.len());
+        for (i, n) in want.iter().enumerate() {
+            assert_eq!(&p.nodes[i], n)
+        }
+    }
+    #[test]
+    fn test_filter_apply() {
+        assert_eq!(Filter::Snake.apply("Meeting Notes"), "meeting_notes");
+        assert_eq!(Filter::Kebab.apply("meeting_notes"), "meeting-notes");
+        assert_eq!(Filter::Title.apply("meeting notes"), "Meeting Notes");
+        assert_eq!(Filter::Upper.apply("a-b"), "A-B");
+        assert_eq!(Filter::Trim.apply("  a b "), "a b");
+        assert_eq!(Filter::Truncate(3).apply("äöüß"), "äöü");
+        assert_eq!(
+            Filter::Replace(" ".to_string(), "".to_string()).apply("a b c"),
+            "abc"
+        );
+    }
+    #[test]
+    fn test_invalid_filters() {
+        for format_string in &[
+            "%0|truncate(x)",
+            "%0|truncate",
+            "%0|replace(a)",
+            "%0|upper(1)",
+            "%0|replace(\"a\",\"b\"",
+        ] {
+            let mut p = FormatParser::new(format_string);
+            assert!(p.parse().is_err());
+        }
+    }
+    #[test]
+    fn test_counter() {
+        let format_string = String::from("2003-02-01_###_#");
+        let want = [
+            Node::Year,
+            Node::Literal("-".to_string()),
+            Node::FebNum,
+            Node::Literal("-".to_string()),
+            Node::Day,
+            Node::Literal("_".to_string()),
+            Node::Counter(3),
+            Node::Literal("_".to_string()),
+            Node::Counter(1),
+        ];
+        let mut p = FormatParser::new(&format_string);
+        assert!(p.parse().is_ok());
+        assert_eq!(p.nodes.len(), want.len());
+        for (i, n) in want.iter().enumerate() {
+            assert_eq!(&p.nodes[i], n)
+        }
+    }
+    #[test]
+    fn test_unpadded() {
+        let format_string = String::from("1-2-2003_February 1st_18");
+        let want = [
+            Node::DayUnpadded,
+            Node::Literal("-".to_string()),
+            Node::FebNumUnpadded,
+            Node::Literal("-".to_string()),
+            Node::Year,
+            Node::Literal("_".to_string()),
+            Node::FebruaryCaps,
+            Node::Literal(" ".to_string()),
+            Node::DayOrdinal,
+            Node::Literal("_".to_string()),
+            Node::Hour,
+        ];
+        let mut p = FormatParser::new(&format_string);
+        assert!(p.parse().is_ok());
+        assert_eq!(p.nodes.len(), want.len());
+        for (i, n) in want.iter().enumerate() {
+            assert_eq!(&p.nodes[i], n)
+        }
+    }
+    #[test]
+    fn test_week_quarter_day_of_year() {
+        let cases: [(&str, &[Node]); 3] = [
+            (
+                "2003-W05",
+                &[Node::IsoYear, Node::Literal("-".to_string()), Node::IsoWeek],
+            ),
+            (
+                "2003-Q1_2003-032",
+                &[
+                    Node::Year,
+                    Node::Literal("-".to_string()),
+                    Node::Quarter,
+                    Node::Literal("_".to_string()),
+                    Node::Year,
+                    Node::Literal("-".to_string()),
+                    Node::DayOfYear,
+                ],
+            ),
+            (
+                "2003-02-01_W05",
+                &[
+                    Node::Year,
+                    Node::Literal("-".to_string()),
+                    Node::FebNum,
+                    Node::Literal("-".to_string()),
+                    Node::Day,
+                    Node::Literal("_".to_string()),
+                    Node::IsoWeek,
+                ],
+            ),
+        ];
+        for (format_string, want) in cases.iter() {
+            let mut p = FormatParser::new(format_string);
+            assert!(p.parse().is_ok());
+            assert_eq!(p.nodes, *want);
+        }
+    }
+    #[test]
+    fn test_epoch() {
+        let format_string = String::from("1044122645_1044122645000");
+        let want = [
+            Node::EpochSeconds,
+            Node::Literal("_".to_string()),
+            Node::EpochMillis,
+        ];
+        let mut p = FormatParser::new(&format_string);
+        assert!(p.parse().is_ok());
+        assert_eq!(p.nodes, want);
+    }
+    #[test]
+    fn test_provided_and_required_identifiers() {
+        let parse = |s: &str| {
+            let mut p = FormatParser::new(s);
+            p.parse().unwrap();
+            p.nodes
+        };
+        let provided = provided_identifiers(&parse("2003-W05"));
+        for i in [
+            Identifier::Year,
+            Identifier::Month,
+            Identifier::Day,
+            Identifier::Weekday,
+            Identifier::DayOfYear,
+            Identifier::Quarter,
+        ] {
+            assert!(provided.contains(&i), "{}", i);
+        }
+        let provided = provided_identifiers(&parse("Q1"));
+        assert!(provided.contains(&Identifier::Month));
+        assert!(!provided.contains(&Identifier::Weekday));
+
+        let required = required_identifiers(&parse("1044122645_(%0|-%0)[_%{x}]"));
+        let want = [
+            Identifier::Year,
+            Identifier::Month,
+            Identifier::Day,
+            Identifier::Fragment(Fragment::Index(0)),
+            Identifier::Fragment(Fragment::Name("x".to_string())),
+        ];
+        assert_eq!(required.into_iter().collect::<Vec<_>>(), want);
+    }
+    #[test]
+    fn test_pattern_errors() {
+        for (format_string, start, end, message) in &[
+            ("2003-FEX-01", 5, 8, "unknown month FEX, did you mean FEB?"),
+            (
+                "2003-Febuary-01",
+                5,
+                12,
+                "unknown month Febuary, did you mean February?",
+            ),
+            (
+                "final_%0",
+                0,
+                5,
+                "after `f` expected `feb` or `february`, use \\f for a literal f",
+            ),
+            (
+                "2003-09",
+                5,
+                7,
+                "after `0` expected `1`, `2`, `3`, `4`, `5`, `6` or `32`, use \\0 for a literal 0",
+            ),
+            ("%0_[]", 3, 5, "optional group must not be empty"),
+            (
+                "%0_%{a b}",
+                3,
+                7,
+                "could not parse hole,   is not allowed in a name",
+            ),
+            ("(%0", 3, 4, "group is missing the closing )"),
+            ("%0_%2_%3", 3, 5, "missing %1"),
+            ("%1_%{1}", 0, 2, "missing %0"),
+            (
+                "%0_%",
+                3,
+                4,
+                "after `%` expected a digit or `{`, use \\% for a literal %",
+            ),
+            (
+                "%0_%x",
+                3,
+                4,
+                "after `%` expected a digit or `{`, use \\% for a literal %",
+            ),
+        ] {
+            let mut p = FormatParser::new(format_string);
+            match p.parse() {
+                Err(MyCustomError::PatternError(pattern, s, e, m)) => {
+                    assert_eq!(&pattern, format_string);
+                    assert_eq!(
+                        (s, e, &m[..]),
+                        (*start, *end, *message),
+                        "{}",
+                        format_string
+                    );
+                }
+                res => panic!("{}: unexpected {:?}", format_string, res),
+            }
+        }
+    }
+    #[test]
+    fn test_pattern_error_display() {
+        let mut p = FormatParser::new("2003-FEX-01");
+        let err = p.parse().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid pattern at character 6: unknown month FEX, did you mean FEB?\n\n\t2003-FEX-01\n\t     ^^^\n"
+        );
+    }
+    #[test]
     fn test_invalid_hole() {
         let format_string = String::from("%2004");
         let mut p = FormatParser::new(&format_string);
         assert!(p.parse().is_err());
     }
+    #[test]
+    fn test_time() {
+        let format_string = String::from("18:04:05_06PM_pm");
+        let want = [
+            Node::Hour,
+            Node::Literal(":".to_string()),
+            Node::Minute,
+            Node::Literal(":".to_string()),
+            Node::Second,
+            Node::Literal("_".to_string()),
+            Node::Hour12,
+            Node::PmAllCaps,
+            Node::Literal("_".to_string()),
+            Node::Pm,
+        ];
+        let mut p = FormatParser::new(&format_string);
+        assert!(p.parse().is_ok());
+        assert_eq!(p.nodes.len(), want.len());
+        for (i, n) in want.iter().enumerate() {
+            assert_eq!(&p.nodes[i], n)
+        }
+    }
+    #[test]
+    fn test_time_keywords_in_literal() {
+        let format_string = String::from(r"\1P_%0");
+        let want = [
+            Node::Literal("1P_".to_string()),
+            Node::Hole(Fragment::Index(0).into()),
+        ];
+        let mut p = FormatParser::new(&format_string);
+        assert!(p.parse().is_ok());
+        assert_eq!(p.nodes.len(), want.len());
+        for (i, n) in want.iter().enumerate() {
+            assert_eq!(&p.nodes[i], n)
+        }
+    }
 }
@@ -0,0 +1,300 @@
+use crate::error::MyCustomError;
+use crate::PlanError;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const HEADER: &str = "# tidy journal";
+const EXTENSION: &str = "journal";
+
+// A rename which was performed, with the time in seconds since January 1,
+// 1970.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Entry {
+    pub time: u64,
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+// Records the renames of a run in a file, one line per rename, so that the run
+// can be undone. Every line is written as soon as the file has been renamed.
+//
+// The file starts with the patterns of the run:
+//
+//   # tidy journal
+//   source 20030201_%0
+//   target %0_2003-02-01
+//   1622548800	/docs/20210601_a.txt	/docs/a_2021-06-01.txt
+//
+// Tabs, newlines and backslashes in patterns and paths are escaped.
+pub struct Journal {
+    pub path: PathBuf,
+    file: fs::File,
+    header: String,
+    entries: Vec<Entry>,
+}
+
+impl Journal {
+    // Creates a new journal in dir, named after the current time.
+    pub fn create(dir: &Path, source: &str, target: &str) -> Result<Journal, MyCustomError> {
+        fs::create_dir_all(dir)?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut n = 0;
+        let path = loop {
+            let path = dir.join(format!(
+                "{:010}-{:09}-{}.{}",
+                now.as_secs(),
+                now.subsec_nanos(),
+                n,
+                EXTENSION
+            ));
+            if !path.exists() {
+                break path;
+            }
+            n += 1;
+        };
+        let mut file = fs::File::create(&path)?;
+        let header = header(source, target);
+        file.write_all(header.as_bytes())?;
+        Ok(Journal {
+            path,
+            file,
+            header,
+            entries: Vec::new(),
+        })
+    }
+
+    // Records a rename with absolute paths, so that it can be undone from any
+    // directory.
+    pub fn record(&mut self, from: &Path, to: &Path) -> Result<(), MyCustomError> {
+        let entry = Entry {
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            from: std::path::absolute(from)?,
+            to: std::path::absolute(to)?,
+        };
+        self.file.write_all(line(&entry).as_bytes())?;
+        self.file.flush()?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    // Removes a rename which has been rolled back. The journal is removed once
+    // it has no renames left.
+    pub fn forget(&mut self, from: &Path, to: &Path) -> Result<(), MyCustomError> {
+        let (from, to) = (std::path::absolute(from)?, std::path::absolute(to)?);
+        self.entries.retain(|e| e.from != from || e.to != to);
+        if self.entries.is_empty() {
+            fs::remove_file(&self.path)?;
+            return Ok(());
+        }
+        let mut contents = self.header.clone();
+        for e in &self.entries {
+            contents.push_str(&line(e));
+        }
+        self.file = fs::File::create(&self.path)?;
+        self.file.write_all(contents.as_bytes())?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+// The patterns and the renames of a journal.
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct Record {
+    pub source: String,
+    pub target: String,
+    pub entries: Vec<Entry>,
+}
+
+pub fn read(path: &Path) -> Result<Record, MyCustomError> {
+    let error = |i: usize, reason: &str| {
+        MyCustomError::ParseError(format!("{}:{}: {}", path.display(), i + 1, reason))
+    };
+    let mut record = Record::default();
+    for (i, line) in fs::read_to_string(path)?.lines().enumerate() {
+        if i == 0 {
+            if line != HEADER {
+                return Err(error(i, "not a journal of tidy"));
+            }
+        } else if let Some(s) = line.strip_prefix("source ") {
+            record.source = unescape(s);
+        } else if let Some(s) = line.strip_prefix("target ") {
+            record.target = unescape(s);
+        } else {
+            let fields: Vec<&str> = line.split('\t').collect();
+            match fields[..] {
+                [time, from, to] => record.entries.push(Entry {
+                    time: time.parse().map_err(|_| error(i, "invalid time"))?,
+                    from: PathBuf::from(unescape(from)),
+                    to: PathBuf::from(unescape(to)),
+                }),
+                _ => return Err(error(i, "expected time, old and new path")),
+            }
+        }
+    }
+    Ok(record)
+}
+
+// Returns the most recent journal in dir which has not been undone.
+pub fn last_journal(dir: &Path) -> Result<PathBuf, MyCustomError> {
+    let mut journals: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(x) => x
+            .filter_map(Result::ok)
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|x| x == EXTENSION))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    journals.sort();
+    journals
+        .pop()
+        .ok_or_else(|| MyCustomError::Default(format!("no journal to undo in {}", dir.display())))
+}
+
+// The renames which were reverted and the files which could not be renamed
+// back.
+#[derive(Debug, Default)]
+pub struct UndoReport {
+    pub reverted: Vec<Entry>,
+    pub failed: Vec<PlanError>,
+}
+
+// Renames the files of a journal back in reverse order. A file is only renamed
+// back if it still has its new name and its old name is free. The journal is
+// marked as undone if every file was renamed back, otherwise it keeps the
+// renames which failed, so that undo can be repeated.
+pub fn undo(path: &Path) -> Result<UndoReport, MyCustomError> {
+    let record = read(path)?;
+    let mut report = UndoReport::default();
+    let mut remaining = Vec::new();
+    for e in record.entries.into_iter().rev() {
+        match rename_back(&e.from, &e.to) {
+            Err(error) => {
+                report.failed.push(PlanError {
+                    path: e.to.clone(),
+                    error,
+                });
+                remaining.push(e);
+            }
+            Ok(()) => report.reverted.push(e),
+        }
+    }
+    if remaining.is_empty() {
+        fs::rename(path, path.with_extension("undone"))?;
+    } else {
+        let mut contents = header(&record.source, &record.target);
+        for e in remaining.iter().rev() {
+            contents.push_str(&line(e));
+        }
+        fs::write(path, contents)?;
+    }
+    Ok(report)
+}
+
+// Renames the file to back to from if it still exists and from is free.
+pub(crate) fn rename_back(from: &Path, to: &Path) -> Result<(), MyCustomError> {
+    let reason = if to.symlink_metadata().is_err() {
+        "it no longer exists".to_string()
+    } else if from.symlink_metadata().is_ok() {
+        "the old name is taken".to_string()
+    } else {
+        match fs::rename(to, from) {
+            Ok(()) => return Ok(()),
+            Err(e) => e.to_string(),
+        }
+    };
+    Err(MyCustomError::UndoError(
+        to.display().to_string(),
+        from.display().to_string(),
+        reason,
+    ))
+}
+
+// Returns the directory for journals, $HOME/.local/share/tidy.
+pub fn default_journal_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local/share/tidy"))
+}
+
+fn header(source: &str, target: &str) -> String {
+    format!(
+        "{}\nsource {}\ntarget {}\n",
+        HEADER,
+        escape(source),
+        escape(target)
+    )
+}
+
+fn line(e: &Entry) -> String {
+    format!(
+        "{}\t{}\t{}\n",
+        e.time,
+        escape(&e.from.to_string_lossy()),
+        escape(&e.to.to_string_lossy())
+    )
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+fn unescape(s: &str) -> String {
+    let mut res = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('t') => res.push('\t'),
+                Some('n') => res.push('\n'),
+                Some(x) => res.push(x),
+                None => res.push('\\'),
+            },
+            x => res.push(x),
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::journal::{last_journal, read, undo, Journal};
+    use crate::MyCustomError;
+
+    #[test]
+    fn test_undo_partially() -> Result<(), MyCustomError> {
+        let dir = std::env::temp_dir().join("tidy_test_journal");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+        let mut journal = Journal::create(&dir.join("journals"), "%0\t2003", "2003_%0")?;
+        for (from, to) in &[("a", "a\tb"), ("c", "d"), ("e", "f")] {
+            std::fs::write(dir.join(to), "")?;
+            journal.record(&dir.join(from), &dir.join(to))?;
+        }
+        let record = read(&journal.path)?;
+        assert_eq!(record.source, "%0\t2003");
+        assert_eq!(record.entries[0].to, dir.join("a\tb"));
+
+        // d is gone and the old name of f is taken.
+        std::fs::remove_file(dir.join("d"))?;
+        std::fs::write(dir.join("e"), "")?;
+        let report = undo(&journal.path)?;
+        assert_eq!(report.reverted.len(), 1);
+        assert_eq!(report.reverted[0].from, dir.join("a"));
+        assert_eq!(report.failed.len(), 2);
+
+        // The journal keeps the renames which could not be undone.
+        assert_eq!(last_journal(&dir.join("journals"))?, journal.path);
+        let record = read(&journal.path)?;
+        let froms: Vec<_> = record.entries.iter().map(|e| e.from.clone()).collect();
+        assert_eq!(froms, vec![dir.join("c"), dir.join("e")]);
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -1,44 +1,291 @@
 use crate::constants as c;
-use crate::filename_parser::FileNameParser;
-use crate::format_parser::FormatParser;
+use crate::counter::Counter;
+use crate::filename_parser::{Data, FileNameParser, ParseOpt};
+use crate::format_parser::{FormatParser, Fragment};
 use ansi_term::Colour::{Green, Red, Yellow};
 use convert_case::{Case, Casing};
-use error::MyCustomError;
+pub use error::MyCustomError;
 use log::debug;
 use std::fs;
 use std::io;
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
+mod calendar;
+mod conflict;
 mod constants;
+mod counter;
 mod error;
 mod filename_parser;
 mod format_parser;
+mod journal;
+mod locale;
+mod strftime;
 
+pub use calendar::UtcOffset;
+pub use conflict::ConflictStrategy;
+pub use counter::CounterScope;
+pub use journal::{default_journal_dir, last_journal, undo, Entry, Journal, UndoReport};
+pub use locale::Locale;
+pub use strftime::Syntax;
+
+#[derive(Default)]
 pub struct RunOpt {
     pub simulate: bool,
     pub force: bool,
     pub review: bool,
+    // First number and scope of the sequence numbers rendered by #.
+    pub counter_start: u32,
+    pub counter_scope: CounterScope,
+    // Languages of month and weekday names in source and target.
+    pub source_locale: Locale,
+    pub target_locale: Locale,
+    // File with further names of months in the source.
+    pub month_aliases: Option<String>,
+    // Two-digit years above the pivot are read as 19xx, all others as 20xx.
+    pub pivot_year: Option<u32>,
+    // Offset of the local time from UTC for epoch timestamps.
+    pub epoch_offset: UtcOffset,
+    // Literals of the source match regardless of case.
+    pub ignore_case: bool,
+    // Dialects in which the source and the target pattern are written.
+    pub source_syntax: Syntax,
+    pub target_syntax: Syntax,
+    // What happens if files would get the same name or a name which is taken.
+    pub on_conflict: ConflictStrategy,
+    // Directory of the journals which record the renames of every run.
+    pub journal_dir: Option<PathBuf>,
+    // Renames all files or, if a rename fails, none.
+    pub atomic: bool,
 }
 
-pub fn run(
+// Options which change how new names are written.
+#[derive(Default)]
+struct RenderOpt {
+    // Language of month and weekday names.
+    locale: Locale,
+    // Offset of the local time from UTC for epoch timestamps.
+    epoch_offset: UtcOffset,
+}
+
+// A file and the name it is renamed to.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Rename {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    // How ambiguous parts of the filename were read, one line each, e.g.
+    // " year 98 read as 1998".
+    pub interpretation: String,
+    // The conflict strategy chose to overwrite the file which has the new
+    // name. Other files are never overwritten.
+    pub overwrite: bool,
+}
+
+// A file which cannot be renamed and the reason why.
+#[derive(Debug)]
+pub struct PlanError {
+    pub path: PathBuf,
+    pub error: MyCustomError,
+}
+
+// The renames of all matching files in a directory, in the order in which they
+// are applied, the files which cannot be renamed and the files which are left
+// alone to resolve a conflict. Planning does not touch any file.
+#[derive(Debug, Default)]
+pub struct RenamePlan {
+    pub renames: Vec<Rename>,
+    pub errors: Vec<PlanError>,
+    pub skipped: Vec<PlanError>,
+}
+
+impl RenamePlan {
+    // Renames the files of the plan in order and stops at the first error.
+    // Files which could not be planned are left alone. Every rename is
+    // recorded in the journal, if any.
+    pub fn apply(&self, mut journal: Option<&mut Journal>) -> Result<(), MyCustomError> {
+        for r in &self.renames {
+            debug!("renaming {} to {}", r.from.display(), r.to.display());
+            rename(r)?;
+            if let Some(j) = journal.as_mut() {
+                j.record(&r.from, &r.to)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Renames the files like apply, but if a rename fails, the files renamed
+    // before are renamed back in reverse order and removed from the journal.
+    // Files which are overwritten are moved to a backup first, which is
+    // restored on rollback and removed once all files are renamed. The error
+    // lists the result of the rollback for every file.
+    pub fn apply_atomic(&self, mut journal: Option<&mut Journal>) -> Result<(), MyCustomError> {
+        let mut backups: Vec<(usize, PathBuf)> = Vec::new();
+        for (i, r) in self.renames.iter().enumerate() {
+            debug!("renaming {} to {}", r.from.display(), r.to.display());
+            let cause = |e: &dyn std::fmt::Display| {
+                format!(
+                    "cannot rename {} to {}: {}",
+                    r.from.display(),
+                    r.to.display(),
+                    e
+                )
+            };
+            if r.overwrite && r.from != r.to && r.to.symlink_metadata().is_ok() {
+                match back_up(&r.to) {
+                    Ok(b) => backups.push((i, b)),
+                    Err(e) => return Err(self.roll_back(i, cause(&e), &backups, journal)),
+                }
+            }
+            if let Err(e) = rename(r) {
+                return Err(self.roll_back(i, cause(&e), &backups, journal));
+            }
+            let recorded = match journal.as_mut() {
+                Some(j) => j.record(&r.from, &r.to),
+                None => Ok(()),
+            };
+            // A rename which cannot be recorded is rolled back as well.
+            if let Err(e) = recorded {
+                return Err(self.roll_back(i + 1, cause(&e), &backups, journal));
+            }
+        }
+        for (_, b) in &backups {
+            if let Err(e) = fs::remove_file(b) {
+                debug!("cannot remove backup {}: {}", b.display(), e);
+            }
+        }
+        Ok(())
+    }
+
+    // Renames the first done files back in reverse order and restores the
+    // files they overwrote, including the backup of a failed rename.
+    fn roll_back(
+        &self,
+        done: usize,
+        cause: String,
+        backups: &[(usize, PathBuf)],
+        mut journal: Option<&mut Journal>,
+    ) -> MyCustomError {
+        let mut rolled_back = Vec::new();
+        let mut failed = Vec::new();
+        for (i, r) in self.renames.iter().enumerate().take(done + 1).rev() {
+            if i < done {
+                debug!("renaming {} back to {}", r.to.display(), r.from.display());
+                match journal::rename_back(&r.from, &r.to) {
+                    Ok(()) => {
+                        if let Some(j) = journal.as_mut() {
+                            if let Err(e) = j.forget(&r.from, &r.to) {
+                                debug!("cannot remove rename from journal: {}", e);
+                            }
+                        }
+                        rolled_back
+                            .push((r.to.display().to_string(), r.from.display().to_string()));
+                    }
+                    Err(e) => failed.push(e.to_string()),
+                }
+            }
+            if let Some((_, b)) = backups.iter().find(|(j, _)| *j == i) {
+                debug!("restoring {} from {}", r.to.display(), b.display());
+                match journal::rename_back(&r.to, b) {
+                    Ok(()) => {
+                        rolled_back.push((b.display().to_string(), r.to.display().to_string()))
+                    }
+                    Err(e) => failed.push(e.to_string()),
+                }
+            }
+        }
+        MyCustomError::RollbackError(cause, rolled_back, failed)
+    }
+
+    // Keeps only the accepted renames. The files which were not accepted keep
+    // their names, which accepted renames may take, so the conflicts are
+    // resolved again. New names would not have been reviewed, so such renames
+    // are skipped instead of suffixed.
+    fn keep(
+        &mut self,
+        accepted: Vec<Rename>,
+        strategy: ConflictStrategy,
+    ) -> Result<(), MyCustomError> {
+        self.renames = accepted;
+        self.skipped.clear();
+        self.errors.clear();
+        let strategy = match strategy {
+            ConflictStrategy::Suffix => ConflictStrategy::Skip,
+            x => x,
+        };
+        conflict::resolve(self, strategy)
+    }
+}
+
+// Renames the file unless its new name is taken by a file which the plan does
+// not overwrite, e.g. one that was created after planning.
+fn rename(r: &Rename) -> Result<(), MyCustomError> {
+    if !r.overwrite && r.from != r.to && r.to.symlink_metadata().is_ok() {
+        return Err(MyCustomError::Default(format!(
+            "{} already exists",
+            r.to.display()
+        )));
+    }
+    fs::rename(&r.from, &r.to)?;
+    Ok(())
+}
+
+// Moves a file which is about to be overwritten to a hidden name next to it,
+// so that it can be restored.
+fn back_up(path: &Path) -> Result<PathBuf, MyCustomError> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let mut n = 0;
+    let backup = loop {
+        let p = path.with_file_name(format!(".{}.tidy-backup-{}", name, n));
+        if p.symlink_metadata().is_err() {
+            break p;
+        }
+        n += 1;
+    };
+    fs::rename(path, &backup)?;
+    Ok(backup)
+}
+
+// Parses the patterns and computes the new name of every file below dir.
+// Errors in the patterns fail the whole plan, errors in single files are
+// recorded in the plan.
+pub fn plan(
     source_template: &str,
     target_template: &str,
     dir: &str,
-    opt: RunOpt,
-) -> Result<(), MyCustomError> {
+    opt: &RunOpt,
+) -> Result<RenamePlan, MyCustomError> {
     let mut source = FormatParser::new(source_template);
+    source.syntax = opt.source_syntax;
     source.parse()?;
     debug!("parsing source success!");
 
     let mut target = FormatParser::new(target_template);
+    target.syntax = opt.target_syntax;
     target.parse()?;
     debug!("parsing target success!");
+    check_compatible(&source, &target)?;
 
-    let mut i: i32 = 0;
-    let entries = WalkDir::new(dir);
+    // Files are visited in a fixed order, so that sequence numbers are the
+    // same for simulated and real runs.
+    let entries = WalkDir::new(dir).sort_by(|a, b| a.file_name().cmp(b.file_name()));
+    let mut counter = Counter::new(opt.counter_start, opt.counter_scope);
+    let parse_opt = ParseOpt {
+        locale: opt.source_locale,
+        month_aliases: match &opt.month_aliases {
+            Some(path) => locale::read_month_aliases(path, opt.source_locale)?,
+            None => Vec::new(),
+        },
+        pivot_year: opt.pivot_year,
+        epoch_offset: opt.epoch_offset,
+        ignore_case: opt.ignore_case,
+    };
+    let render_opt = RenderOpt {
+        locale: opt.target_locale,
+        epoch_offset: opt.epoch_offset,
+    };
 
+    let mut plan = RenamePlan::default();
     debug!("looping over files");
     for entry in entries
         .into_iter()
@@ -46,71 +293,138 @@ pub fn run(
         .filter(|e| !e.file_type().is_dir())
     {
         debug!("file: {}", entry.path().display());
-        let new_filename_no_ext = match process(&source, &target, &stem_for_entry(&entry)) {
+        let d = match stem_for_entry(&entry).and_then(|x| parse(&source, &x, &parse_opt)) {
             Ok(x) => x,
-            Err(x) => {
-                if opt.force {
-                    print!(
-                        "{}",
-                        Yellow.paint(format!(
-                            "WARN: skipping processing of file {} because of error: {}\n",
-                            entry.path().to_str().unwrap(),
-                            x
-                        ))
-                    );
-                    continue;
-                } else {
-                    print!(
-                        "{}",
-                        Red.paint(format!(
-                            "\nERR: aborting processing. Error in file {}\n\n\tHint:\n\n\tUse flag --ignore to ignore errors like this one.\n\n",
-                            entry.path().to_str().unwrap()
-                        ))
-                    );
-                    return Err(x);
-                }
+            Err(error) => {
+                plan.errors.push(PlanError {
+                    path: entry.path().to_path_buf(),
+                    error,
+                });
+                continue;
             }
         };
         let p = entry.path();
-        let a = p.to_str().ok_or(MyCustomError::PathError)?;
-        let ext = match entry.path().extension() {
-            Some(x) => x.to_str(),
-            None => None,
-        }
-        .ok_or(MyCustomError::Default("extension error".to_string()))?;
-        let b = p
-            .parent()
-            .ok_or(MyCustomError::PathError)?
-            .join(Path::new(&(new_filename_no_ext + "." + ext)));
-        i = i + 1;
-        if opt.simulate {
+        let ext = match p.extension().and_then(|x| x.to_str()) {
+            Some(x) => x,
+            None => {
+                plan.errors.push(PlanError {
+                    path: p.to_path_buf(),
+                    error: MyCustomError::Default("extension error".to_string()),
+                });
+                continue;
+            }
+        };
+        let parent = p.parent().ok_or(MyCustomError::PathError)?;
+        // The sequence number is taken after the checks above, so that files
+        // which cannot be read leave no gaps. Files which are left alone later
+        // because of a conflict or in review keep their numbers, so that the
+        // new names of the others do not change after they were shown.
+        let new_filename_no_ext = render(
+            &target.nodes,
+            &d,
+            counter.next(entry.path(), &d),
+            &render_opt,
+        );
+        let to = parent.join(Path::new(&(new_filename_no_ext + "." + ext)));
+        plan.renames.push(Rename {
+            from: p.to_path_buf(),
+            to,
+            interpretation: interpretation(&d),
+            overwrite: false,
+        });
+    }
+    conflict::resolve(&mut plan, opt.on_conflict)?;
+    Ok(plan)
+}
+
+pub fn run(
+    source_template: &str,
+    target_template: &str,
+    dir: &str,
+    opt: RunOpt,
+) -> Result<(), MyCustomError> {
+    let mut plan = plan(source_template, target_template, dir, &opt)?;
+    report(&mut plan, opt.force)?;
+
+    if opt.simulate {
+        for r in &plan.renames {
             print!(
-                "{}\n{}\n\n",
-                Red.paint(format!("-{}", &a)),
-                Green.paint(format!("+{}", &b.to_str().unwrap()))
+                "{}\n{}\n{}\n",
+                Red.paint(format!("-{}", r.from.display())),
+                Green.paint(format!("+{}", r.to.display())),
+                Yellow.paint(&r.interpretation)
             );
-            continue;
         }
-        if opt.review == true {
+        return Ok(());
+    }
+    if opt.review {
+        let mut accepted = Vec::new();
+        for r in plan.renames.drain(..) {
             clear_screen();
             print!(
-                "Do you want to rename this file?\n\n{}\n{}\n\n",
-                Red.paint(format!("-{}", &a)),
-                Green.paint(format!("+{}", &b.to_str().unwrap()))
+                "Do you want to rename this file?\n\n{}\n{}\n{}\n",
+                Red.paint(format!("-{}", r.from.display())),
+                Green.paint(format!("+{}", r.to.display())),
+                Yellow.paint(&r.interpretation)
             );
             match ask_user_input() {
-                UserInput::Abort => {
-                    break;
-                }
-                UserInput::Accept => (),
-                UserInput::Skip => {
-                    continue;
-                }
+                UserInput::Abort => break,
+                UserInput::Accept => accepted.push(r),
+                UserInput::Skip => (),
             }
         }
-        fs::rename(a, b)?;
+        plan.keep(accepted, opt.on_conflict)?;
+        report(&mut plan, opt.force)?;
+    }
+    let mut journal = match &opt.journal_dir {
+        Some(dir) if !plan.renames.is_empty() => {
+            Some(Journal::create(dir, source_template, target_template)?)
+        }
+        _ => None,
+    };
+    if opt.atomic {
+        plan.apply_atomic(journal.as_mut())
+    } else {
+        plan.apply(journal.as_mut())
     }
-    return Ok(());
+}
+
+// Prints the skipped files and the files which cannot be renamed. No file is
+// renamed if any file cannot be renamed, unless errors are ignored.
+fn report(plan: &mut RenamePlan, force: bool) -> Result<(), MyCustomError> {
+    for e in &plan.skipped {
+        print!(
+            "{}",
+            Yellow.paint(format!(
+                "WARN: skipping processing of file {} because of conflict: {}\n",
+                e.path.display(),
+                e.error
+            ))
+        );
+    }
+    if force {
+        for e in &plan.errors {
+            print!(
+                "{}",
+                Yellow.paint(format!(
+                    "WARN: skipping processing of file {} because of error: {}\n",
+                    e.path.display(),
+                    e.error
+                ))
+            );
+        }
+    } else if !plan.errors.is_empty() {
+        let e = plan.errors.remove(0);
+        print!(
+            "{}",
+            Red.paint(format!(
+                "\nERR: aborting processing. Error in file {}\n\n\tHint:\n\n\tUse flag --ignore to ignore errors like this one.\n\n",
+                e.path.display()
+            ))
+        );
+        return Err(e.error);
+    }
+    Ok(())
 }
 
 enum UserInput {
@@ -144,98 +458,282 @@ fn ask_user_input() -> UserInput {
     }
 }
 
-fn stem_for_entry(entry: &walkdir::DirEntry) -> String {
-    return String::from(entry.path().file_stem().unwrap().to_str().unwrap());
+// Checks that every identifier of the target can be taken or computed from the
+// source, so that no file is touched if the patterns do not fit together.
+fn check_compatible(source: &FormatParser, target: &FormatParser) -> Result<(), MyCustomError> {
+    let provided = format_parser::provided_identifiers(&source.nodes);
+    let missing: Vec<String> = format_parser::required_identifiers(&target.nodes)
+        .difference(&provided)
+        .map(|x| x.to_string())
+        .collect();
+    if !missing.is_empty() {
+        return Err(MyCustomError::MissingIdentifiersError(missing));
+    }
+    Ok(())
+}
+
+// Describes how ambiguous parts of the filename were read, e.g. the century of
+// a two-digit year. Every line ends with a newline.
+fn interpretation(d: &Data) -> String {
+    match d.short_year {
+        Some(y) => format!(" year {:02} read as {}\n", y, d.year.unwrap()),
+        None => String::new(),
+    }
+}
+
+fn stem_for_entry(entry: &walkdir::DirEntry) -> Result<String, MyCustomError> {
+    let stem = entry.path().file_stem().ok_or(MyCustomError::PathError)?;
+    stem.to_str()
+        .map(String::from)
+        .ok_or_else(|| MyCustomError::Default("name is not valid UTF-8".to_string()))
 }
 
+#[cfg(test)]
 fn process(
     template: &FormatParser,
     target: &FormatParser,
     s: &str,
 ) -> Result<String, MyCustomError> {
-    let mut f = FileNameParser::new(s, template.holes);
-    f.parse(&template.nodes)?;
+    let d = parse(template, s, &ParseOpt::default())?;
+    Ok(render(&target.nodes, &d, 1, &RenderOpt::default()))
+}
 
-    let mut nodes = target.nodes.iter().peekable();
+fn parse(template: &FormatParser, s: &str, opt: &ParseOpt) -> Result<Data, MyCustomError> {
+    let mut f = FileNameParser::new(s, template.holes, opt);
+    f.parse(&template.nodes)?;
+    Ok(f.d)
+}
 
+fn render(nodes: &[format_parser::Node], d: &Data, counter: u32, opt: &RenderOpt) -> String {
     let mut res: String = String::from("");
-    loop {
-        match nodes.next() {
-            Some(n) => match n {
-                format_parser::Node::Year => {
-                    res = format!(
-                        "{}{}",
-                        res,
-                        f.d.year.as_ref().unwrap().iter().collect::<String>()
-                    )
-                }
-                format_parser::Node::YearShort => {
-                    res = format!(
-                        "{}{}",
-                        res,
-                        f.d.year.as_ref().unwrap()[2..].iter().collect::<String>()
-                    )
-                }
-                format_parser::Node::FebNum => {
-                    res = format!("{}{}", res, c::IRTOFEBNUM[f.d.month.as_ref().unwrap()])
-                }
-                format_parser::Node::Feb => {
-                    res = format!("{}{}", res, c::IRTOFEB[f.d.month.as_ref().unwrap()])
-                }
-                format_parser::Node::FebCaps => {
-                    res = format!(
-                        "{}{}",
-                        res,
-                        c::IRTOFEB[f.d.month.as_ref().unwrap()]
-                            .to_string()
-                            .to_case(Case::Title)
-                    )
-                }
-                format_parser::Node::FebAllCaps => {
-                    res = format!(
-                        "{}{}",
-                        res,
-                        c::IRTOFEB[f.d.month.as_ref().unwrap()]
-                            .to_string()
-                            .to_case(Case::Upper)
-                    )
-                }
-                format_parser::Node::February => {
-                    res = format!("{}{}", res, c::IRTOFEBRUARY[f.d.month.as_ref().unwrap()])
-                }
-                format_parser::Node::FebruaryCaps => {
-                    res = format!(
-                        "{}{}",
-                        res,
-                        c::IRTOFEBRUARY[f.d.month.as_ref().unwrap()]
-                            .to_string()
-                            .to_case(Case::Title)
-                    )
+    let month = || calendar::month_number(d.month.unwrap());
+    for n in nodes {
+        match n {
+            format_parser::Node::Year => res = format!("{}{:04}", res, d.year.unwrap()),
+            format_parser::Node::YearShort => {
+                res = format!("{}{:02}", res, d.year.unwrap().rem_euclid(100))
+            }
+            format_parser::Node::FebNum => {
+                res = format!("{}{}", res, c::IRTOFEBNUM[d.month.as_ref().unwrap()])
+            }
+            format_parser::Node::Feb => {
+                res = format!("{}{}", res, opt.locale.month_abbreviation(d.month.unwrap()))
+            }
+            format_parser::Node::FebCaps => {
+                res = format!(
+                    "{}{}",
+                    res,
+                    opt.locale
+                        .month_abbreviation(d.month.unwrap())
+                        .to_string()
+                        .to_case(Case::Title)
+                )
+            }
+            format_parser::Node::FebAllCaps => {
+                res = format!(
+                    "{}{}",
+                    res,
+                    opt.locale
+                        .month_abbreviation(d.month.unwrap())
+                        .to_string()
+                        .to_case(Case::Upper)
+                )
+            }
+            format_parser::Node::February => {
+                res = format!("{}{}", res, opt.locale.month(d.month.unwrap()))
+            }
+            format_parser::Node::FebruaryCaps => {
+                res = format!(
+                    "{}{}",
+                    res,
+                    opt.locale
+                        .month(d.month.unwrap())
+                        .to_string()
+                        .to_case(Case::Title)
+                )
+            }
+            format_parser::Node::FebruaryAllCaps => {
+                res = format!(
+                    "{}{}",
+                    res,
+                    opt.locale
+                        .month(d.month.unwrap())
+                        .to_string()
+                        .to_case(Case::Upper)
+                )
+            }
+            format_parser::Node::Day => res = format!("{}{:02}", res, d.day.unwrap()),
+            format_parser::Node::DayUnpadded => res = format!("{}{}", res, d.day.unwrap()),
+            format_parser::Node::DayOrdinal => {
+                let day = d.day.unwrap();
+                res = format!("{}{}{}", res, day, calendar::ordinal_suffix(day))
+            }
+            format_parser::Node::FebNumUnpadded => {
+                res = format!("{}{}", res, calendar::month_number(d.month.unwrap()))
+            }
+            format_parser::Node::Hole(h) => {
+                let hole = h.filters.iter().fold(
+                    d.holes.get(&h.fragment).cloned().unwrap_or_default(),
+                    |s, f| f.apply(&s),
+                );
+                res = format!("{}{}", res, hole)
+            }
+            format_parser::Node::Literal(l) => res = format!("{}{}", res, l),
+            format_parser::Node::Sat => {
+                res = format!(
+                    "{}{}",
+                    res,
+                    opt.locale.weekday_abbreviation(d.weekday().unwrap())
+                )
+            }
+            format_parser::Node::SatCaps => {
+                res = format!(
+                    "{}{}",
+                    res,
+                    opt.locale
+                        .weekday_abbreviation(d.weekday().unwrap())
+                        .to_string()
+                        .to_case(Case::Title)
+                )
+            }
+            format_parser::Node::SatAllCaps => {
+                res = format!(
+                    "{}{}",
+                    res,
+                    opt.locale
+                        .weekday_abbreviation(d.weekday().unwrap())
+                        .to_string()
+                        .to_case(Case::Upper)
+                )
+            }
+            format_parser::Node::Saturday => {
+                res = format!("{}{}", res, opt.locale.weekday(d.weekday().unwrap()))
+            }
+            format_parser::Node::SaturdayCaps => {
+                res = format!(
+                    "{}{}",
+                    res,
+                    opt.locale
+                        .weekday(d.weekday().unwrap())
+                        .to_string()
+                        .to_case(Case::Title)
+                )
+            }
+            format_parser::Node::SaturdayAllCaps => {
+                res = format!(
+                    "{}{}",
+                    res,
+                    opt.locale
+                        .weekday(d.weekday().unwrap())
+                        .to_string()
+                        .to_case(Case::Upper)
+                )
+            }
+            format_parser::Node::Hour => res = format!("{}{:02}", res, d.hour.as_ref().unwrap()),
+            format_parser::Node::Hour12 => {
+                res = format!("{}{:02}", res, (d.hour.unwrap() + 11) % 12 + 1)
+            }
+            format_parser::Node::Minute => {
+                res = format!("{}{:02}", res, d.minute.as_ref().unwrap())
+            }
+            format_parser::Node::Second => {
+                res = format!("{}{:02}", res, d.second.as_ref().unwrap())
+            }
+            format_parser::Node::Pm => {
+                res = format!("{}{}", res, if d.hour.unwrap() < 12 { "am" } else { "pm" })
+            }
+            format_parser::Node::PmAllCaps => {
+                res = format!("{}{}", res, if d.hour.unwrap() < 12 { "AM" } else { "PM" })
+            }
+            format_parser::Node::IsoYear => {
+                let (year, _) = calendar::iso_week(d.year.unwrap(), month(), d.day.unwrap());
+                res = format!("{}{:04}", res, year)
+            }
+            format_parser::Node::IsoWeek => {
+                let (_, week) = calendar::iso_week(d.year.unwrap(), month(), d.day.unwrap());
+                res = format!("{}W{:02}", res, week)
+            }
+            format_parser::Node::Quarter => res = format!("{}Q{}", res, calendar::quarter(month())),
+            format_parser::Node::DayOfYear => {
+                let n = calendar::day_of_year(d.year.unwrap(), month(), d.day.unwrap());
+                res = format!("{}{:03}", res, n)
+            }
+            format_parser::Node::EpochSeconds => {
+                res = format!("{}{}", res, timestamp(d, opt.epoch_offset))
+            }
+            format_parser::Node::EpochMillis => {
+                res = format!(
+                    "{}{}{:03}",
+                    res,
+                    timestamp(d, opt.epoch_offset),
+                    d.millisecond.unwrap_or(0)
+                )
+            }
+            format_parser::Node::Optional(group) => {
+                if !is_empty_group(group, d) {
+                    res = format!("{}{}", res, render(group, d, counter, opt))
                 }
-                format_parser::Node::FebruaryAllCaps => {
-                    res = format!(
-                        "{}{}",
-                        res,
-                        c::IRTOFEBRUARY[f.d.month.as_ref().unwrap()]
-                            .to_string()
-                            .to_case(Case::Upper)
-                    )
+            }
+            // Target patterns always use the first alternative.
+            format_parser::Node::Alternation(branches) => {
+                res = format!("{}{}", res, render(&branches[0], d, counter, opt))
+            }
+            format_parser::Node::Counter(width) => {
+                res = format!("{}{:0width$}", res, counter, width = width)
+            }
+        }
+    }
+    res
+}
+
+// Returns the seconds since January 1, 1970 UTC of the local date and time. A
+// missing time counts as midnight.
+fn timestamp(d: &Data, offset: UtcOffset) -> i64 {
+    let days = calendar::days_from_date(
+        d.year.unwrap(),
+        calendar::month_number(d.month.unwrap()),
+        d.day.unwrap(),
+    );
+    let seconds = d.hour.unwrap_or(0) * 3600 + d.minute.unwrap_or(0) * 60 + d.second.unwrap_or(0);
+    days * 86400 + seconds as i64 - offset.0 as i64
+}
+
+// A group is empty if it contains fragments and all of them are empty.
+fn is_empty_group(nodes: &[format_parser::Node], d: &Data) -> bool {
+    let mut fragments = Vec::new();
+    collect_fragments(nodes, &mut fragments);
+    !fragments.is_empty()
+        && fragments
+            .iter()
+            .all(|h| d.holes.get(h).is_none_or(|v| v.is_empty()))
+}
+
+fn collect_fragments<'a>(nodes: &'a [format_parser::Node], fragments: &mut Vec<&'a Fragment>) {
+    for n in nodes {
+        match n {
+            format_parser::Node::Hole(h) => fragments.push(&h.fragment),
+            format_parser::Node::Optional(group) => collect_fragments(group, fragments),
+            format_parser::Node::Alternation(branches) => {
+                for b in branches {
+                    collect_fragments(b, fragments)
                 }
-                format_parser::Node::Day => res = format!("{}{}", res, f.d.day.as_ref().unwrap()),
-                format_parser::Node::Hole(h) => res = format!("{}{}", res, f.d.holes[*h as usize]),
-                format_parser::Node::Literal(l) => res = format!("{}{}", res, l),
-            },
-            None => break,
+            }
+            _ => (),
         }
     }
-    return Ok(res);
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::filename_parser::ParseOpt;
     use crate::process;
     use crate::FormatParser;
+    use crate::Locale;
     use crate::MyCustomError;
+    use crate::Syntax;
+    use crate::UtcOffset;
+    use crate::{check_compatible, parse, plan, render, RenderOpt, RunOpt};
+    use crate::{last_journal, undo, ConflictStrategy, Journal, Rename, RenamePlan};
     macro_rules! testify {
         ($name:ident, $($source:expr, $target:expr, $filename:expr, $want:expr),+) => {
             #[test]
@@ -315,4 +813,641 @@ mod tests {
         "foo-bar_01.01.1984",
         "1984-01-01-foo-bar"
     );
+    testify!(
+        named_holes,
+        "2003-02-01_%{client}_%{title}",
+        "%{client}_%{title}_2003-02-01",
+        "2021-06-01_acme_invoice",
+        "acme_invoice_2021-06-01"
+    );
+    testify!(
+        many_holes,
+        "%0.%1.%2.%3.%4.%5.%6.%7.%8.%9.%10.%11",
+        "%11-%10-%9-%8-%7-%6-%5-%4-%3-%2-%1-%{0}",
+        "a.b.c.d.e.f.g.h.i.j.k.l",
+        "l-k-j-i-h-g-f-e-d-c-b-a"
+    );
+    testify!(
+        optional_present,
+        "2003-02-01_%0[_%1]",
+        "%0[-%1]_2003-02-01",
+        "2021-06-01_acme_invoice",
+        "acme-invoice_2021-06-01"
+    );
+    testify!(
+        optional_absent,
+        "2003-02-01_%0[_%1]",
+        "%0[-%1]_2003-02-01",
+        "2021-06-01_acme",
+        "acme_2021-06-01"
+    );
+    testify!(
+        optional_alternation_absent,
+        "%0[(_%1|-%1)]",
+        "%0%1",
+        "abc",
+        "abc"
+    );
+    testify!(
+        optional_literal_only,
+        "2003-02-01_%0",
+        "%0[_done]_2003-02-01",
+        "2021-06-01_acme",
+        "acme_done_2021-06-01"
+    );
+    testify!(
+        alternation_dots,
+        "(01.02.2003|2003-02-01)_%0",
+        "2003-02-01_%0",
+        "01.06.2021_foo",
+        "2021-06-01_foo"
+    );
+    testify!(
+        alternation_dashes,
+        "(01.02.2003|2003-02-01)_%0",
+        "2003-02-01_%0",
+        "2021-06-01_foo",
+        "2021-06-01_foo"
+    );
+    testify!(
+        hole_constraints,
+        "INV%0:d%1:a",
+        "%1_%0",
+        "INV12345ACME",
+        "ACME_12345"
+    );
+    testify!(
+        filters,
+        "2003-02-01_%0",
+        r#"2003-02-01_%0|trim|replace(" ","-")|lower|truncate(13)"#,
+        "2021-06-01_ Meeting Notes Berlin",
+        "2021-06-01_meeting-notes"
+    );
+    testify!(
+        filter_case,
+        "%0_%1",
+        "%0|snake_%1|pascal",
+        "Meeting Notes_project plan",
+        "meeting_notes_ProjectPlan"
+    );
+    testify!(
+        counter,
+        "20030201_%0",
+        "2003-02-01_###",
+        "20210601_scan",
+        "2021-06-01_001"
+    );
+    testify!(to_unpadded, "20030201", "1-2-2003", "20210601", "1-6-2021");
+    testify!(
+        unpadded_in_word,
+        "20030201_%0",
+        "%0_v1_rev12_1a",
+        "20210607_foo",
+        "foo_v1_rev12_1a"
+    );
+    testify!(
+        from_unpadded,
+        "1-2-2003",
+        "2003-02-01",
+        "1-6-2021",
+        "2021-06-01"
+    );
+    testify!(
+        to_ordinal,
+        "20030201",
+        "February 1st 2003",
+        "20210622",
+        "June 22nd 2021"
+    );
+    testify!(
+        from_ordinal,
+        "February 1st 2003",
+        "2003-02-01",
+        "June 13th 2021",
+        "2021-06-13"
+    );
+    testify!(
+        escaped_literals,
+        r"\final_v\2_%0",
+        r"%0_v\2_\FA",
+        "final_v2_report",
+        "report_v2_FA"
+    );
+    testify!(
+        weekday_from_date,
+        "2003-02-01_%0",
+        "Sat_2003-02-01_%0",
+        "2021-06-07_standup",
+        "Mon_2021-06-07_standup"
+    );
+    testify!(
+        weekday_long,
+        "20030201",
+        "SATURDAY_01.02.2003",
+        "20000229",
+        "TUESDAY_29.02.2000"
+    );
+    testify!(
+        weekday_from_name,
+        "Sat_2003-02-01_%0",
+        "2003-02-01_%0",
+        "Mon_2021-06-07_standup",
+        "2021-06-07_standup"
+    );
+    testify!(
+        weekday_in_word,
+        "20030201_%0",
+        "%0_compensation_design",
+        "20210607_foo",
+        "foo_compensation_design"
+    );
+    testify!(
+        weekday_keep,
+        "sat_%0",
+        "%0_saturday",
+        "Wed_standup",
+        "standup_wednesday"
+    );
+    testify!(
+        time_24h,
+        "20030201_180405",
+        "2003-02-01_18.04.05",
+        "20210601_143502",
+        "2021-06-01_14.35.02"
+    );
+    testify!(
+        time_to_12h,
+        "20030201_180405",
+        "2003-02-01_06.04PM",
+        "20210601_003502",
+        "2021-06-01_12.35AM"
+    );
+    testify!(
+        time_from_12h,
+        "06-04-05pm_%0",
+        "18:04:05_%0",
+        "12-15-00am_scan",
+        "00:15:00_scan"
+    );
+
+    testify!(
+        time_in_word,
+        "20030201_%0",
+        "%0_development_report18",
+        "20210607_foo",
+        "foo_development_report18"
+    );
+
+    testify!(
+        week_to_date,
+        "2003-W05",
+        "2003-02-01",
+        "2021-W22",
+        "2021-05-31"
+    );
+    testify!(
+        date_to_week,
+        "2003-02-01",
+        "2003-W05_Q1_032",
+        "2021-01-01",
+        "2020-W53_Q1_001"
+    );
+    testify!(
+        week_with_weekday,
+        "2003-W05_Saturday",
+        "01.02.2003",
+        "2021-W22_friday",
+        "04.06.2021"
+    );
+    testify!(
+        quarter_in_word,
+        "20030201_%0",
+        "%0_Q1report_Q12",
+        "20210607_foo",
+        "foo_Q1report_Q12"
+    );
+    testify!(quarter, "2003-Q1", "2003-02-01", "2021-Q2", "2021-04-01");
+    testify!(
+        quarter_with_month,
+        "Q1_feb_%0",
+        "01_%0",
+        "Q2_jun_report",
+        "01_report"
+    );
+    testify!(
+        quarter_with_month_weekday,
+        "2003-Q1_feb_%0",
+        "sat_%0",
+        "2021-Q2_jun_report",
+        "tue_report"
+    );
+    testify!(
+        day_of_year,
+        "2003-032",
+        "2003-02-01_Q1",
+        "2021-152",
+        "2021-06-01_Q2"
+    );
+
+    testify!(
+        from_epoch,
+        "1044122645_%0",
+        "2003-02-01_18.04.05_%0",
+        "1622548800_backup",
+        "2021-06-01_12.00.00_backup"
+    );
+    testify!(
+        to_epoch,
+        "2003-02-01_18.04.05",
+        "1044122645",
+        "2021-06-01_12.00.00",
+        "1622548800"
+    );
+    testify!(
+        epoch_date_only,
+        "20030201",
+        "1044122645000",
+        "20210601",
+        "1622505600000"
+    );
+    testify!(
+        epoch_millis,
+        "1044122645000",
+        "1044122645000",
+        "1622548800123",
+        "1622548800123"
+    );
+
+    #[test]
+    fn test_epoch_offset() -> Result<(), MyCustomError> {
+        let mut source = FormatParser::new("1044122645");
+        source.parse()?;
+        let mut target = FormatParser::new("2003-02-01_18.04_1044122645");
+        target.parse()?;
+        let opt = ParseOpt {
+            epoch_offset: UtcOffset(7200),
+            ..ParseOpt::default()
+        };
+        let d = parse(&source, "1622548800", &opt)?;
+        let render_opt = RenderOpt {
+            epoch_offset: UtcOffset(7200),
+            ..RenderOpt::default()
+        };
+        assert_eq!(
+            render(&target.nodes, &d, 1, &render_opt),
+            "2021-06-01_14.00_1622548800"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_and_apply() -> Result<(), MyCustomError> {
+        let dir = std::env::temp_dir().join("tidy_test_plan");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+        for name in &["20210601_a.txt", "20210602_b.txt", "notes.txt"] {
+            std::fs::write(dir.join(name), "")?;
+        }
+        let opt = RunOpt {
+            counter_start: 1,
+            ..Default::default()
+        };
+        let p = plan("20030201_%0", "%0_2003-02-01", dir.to_str().unwrap(), &opt)?;
+        let renames: Vec<_> = p
+            .renames
+            .iter()
+            .map(|r| (r.from.clone(), r.to.clone()))
+            .collect();
+        assert_eq!(
+            renames,
+            vec![
+                (dir.join("20210601_a.txt"), dir.join("a_2021-06-01.txt")),
+                (dir.join("20210602_b.txt"), dir.join("b_2021-06-02.txt")),
+            ]
+        );
+        assert_eq!(p.errors.len(), 1);
+        assert_eq!(p.errors[0].path, dir.join("notes.txt"));
+        // Planning leaves the files alone.
+        assert!(dir.join("20210601_a.txt").exists());
+
+        let journal_dir = std::env::temp_dir().join("tidy_test_plan_journals");
+        let _ = std::fs::remove_dir_all(&journal_dir);
+        let mut journal = Journal::create(&journal_dir, "20030201_%0", "%0_2003-02-01")?;
+        p.apply(Some(&mut journal))?;
+        let mut names: Vec<_> = std::fs::read_dir(&dir)?
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec!["a_2021-06-01.txt", "b_2021-06-02.txt", "notes.txt"]
+        );
+
+        assert_eq!(last_journal(&journal_dir)?, journal.path);
+        let report = undo(&journal.path)?;
+        assert_eq!(report.reverted.len(), 2);
+        assert!(report.failed.is_empty());
+        assert!(dir.join("20210601_a.txt").exists());
+        assert!(dir.join("20210602_b.txt").exists());
+        // The journal is marked as undone.
+        assert!(last_journal(&journal_dir).is_err());
+        std::fs::remove_dir_all(&dir)?;
+        std::fs::remove_dir_all(&journal_dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_counter_without_gaps() -> Result<(), MyCustomError> {
+        let dir = std::env::temp_dir().join("tidy_test_plan_counter");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+        for name in &["20210601_a.txt", "20210601_b", "20210601_c.txt"] {
+            std::fs::write(dir.join(name), "")?;
+        }
+        let opt = RunOpt {
+            counter_start: 1,
+            ..Default::default()
+        };
+        let p = plan("20030201_%0", "%0_#", dir.to_str().unwrap(), &opt)?;
+        let renames: Vec<_> = p.renames.iter().map(|r| r.to.clone()).collect();
+        assert_eq!(renames, vec![dir.join("a_1.txt"), dir.join("c_2.txt")]);
+        assert_eq!(p.errors[0].path, dir.join("20210601_b"));
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_plan_invalid_utf8() -> Result<(), MyCustomError> {
+        use std::os::unix::ffi::OsStrExt;
+        let dir = std::env::temp_dir().join("tidy_test_plan_utf8");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+        let bad = dir.join(std::ffi::OsStr::from_bytes(b"20210601_bad\xff.txt"));
+        std::fs::write(&bad, "")?;
+        std::fs::write(dir.join("20210601_a.txt"), "")?;
+        let p = plan(
+            "20030201_%0",
+            "%0_2003",
+            dir.to_str().unwrap(),
+            &RunOpt::default(),
+        )?;
+        assert_eq!(p.renames.len(), 1);
+        assert_eq!(p.errors[0].path, bad);
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_review_skip_then_accept() -> Result<(), MyCustomError> {
+        let dir = std::env::temp_dir().join("tidy_test_review");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("a.txt"), "A")?;
+        std::fs::write(dir.join("a_.txt"), "B")?;
+        let mut p = plan("%0", "%0_", dir.to_str().unwrap(), &RunOpt::default())?;
+        assert_eq!(p.renames.len(), 2);
+
+        // a_.txt is skipped and keeps the name a.txt was going to take.
+        let accepted = p
+            .renames
+            .iter()
+            .filter(|r| r.from == dir.join("a.txt"))
+            .cloned()
+            .collect();
+        p.keep(accepted, ConflictStrategy::Suffix)?;
+        assert!(p.renames.is_empty());
+        assert_eq!(p.skipped[0].path, dir.join("a.txt"));
+
+        // A file which took the new name after planning is not overwritten.
+        let p = RenamePlan {
+            renames: vec![Rename {
+                from: dir.join("a.txt"),
+                to: dir.join("a_.txt"),
+                interpretation: String::new(),
+                overwrite: false,
+            }],
+            ..Default::default()
+        };
+        assert!(p.apply(None).is_err());
+        assert_eq!(std::fs::read_to_string(dir.join("a_.txt"))?, "B");
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_atomic() -> Result<(), MyCustomError> {
+        let dir = std::env::temp_dir().join("tidy_test_atomic");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+        for name in &["a", "b", "c"] {
+            std::fs::write(dir.join(name), "")?;
+        }
+        // The last file vanishes after planning.
+        std::fs::remove_file(dir.join("c"))?;
+        let p = RenamePlan {
+            renames: ["a", "b", "c"]
+                .iter()
+                .map(|name| Rename {
+                    from: dir.join(name),
+                    to: dir.join(format!("{}_new", name)),
+                    interpretation: String::new(),
+                    overwrite: false,
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let journal_dir = dir.join("journals");
+        let mut journal = Journal::create(&journal_dir, "%0", "%0_new")?;
+        match p.apply_atomic(Some(&mut journal)) {
+            Err(MyCustomError::RollbackError(cause, rolled_back, failed)) => {
+                assert!(cause.starts_with(&format!("cannot rename {}", dir.join("c").display())));
+                assert_eq!(rolled_back.len(), 2);
+                assert!(failed.is_empty());
+            }
+            res => panic!("unexpected {:?}", res),
+        }
+        assert!(dir.join("a").exists());
+        assert!(dir.join("b").exists());
+        assert!(!dir.join("a_new").exists());
+        // Nothing is left to undo.
+        assert!(last_journal(&journal_dir).is_err());
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_atomic_restores_overwritten() -> Result<(), MyCustomError> {
+        let dir = std::env::temp_dir().join("tidy_test_atomic_overwrite");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("a"), "A")?;
+        std::fs::write(dir.join("x"), "X")?;
+        let rename = |from: &str, to: &str| Rename {
+            from: dir.join(from),
+            to: dir.join(to),
+            interpretation: String::new(),
+            overwrite: true,
+        };
+        // b does not exist, so the run is rolled back and x is restored.
+        let p = RenamePlan {
+            renames: vec![rename("a", "x"), rename("b", "y")],
+            ..Default::default()
+        };
+        match p.apply_atomic(None) {
+            Err(MyCustomError::RollbackError(_, rolled_back, failed)) => {
+                assert_eq!(rolled_back.len(), 2);
+                assert!(failed.is_empty());
+            }
+            res => panic!("unexpected {:?}", res),
+        }
+        assert_eq!(std::fs::read_to_string(dir.join("a"))?, "A");
+        assert_eq!(std::fs::read_to_string(dir.join("x"))?, "X");
+
+        // Without an error the overwritten file is gone.
+        let p = RenamePlan {
+            renames: vec![rename("a", "x")],
+            ..Default::default()
+        };
+        p.apply_atomic(None)?;
+        let names: Vec<_> = std::fs::read_dir(&dir)?
+            .map(|e| e.map(|e| e.file_name()))
+            .collect::<Result<_, _>>()?;
+        assert_eq!(names, vec!["x"]);
+        assert_eq!(std::fs::read_to_string(dir.join("x"))?, "A");
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_mixed_syntax() -> Result<(), MyCustomError> {
+        let cases = [
+            (
+                Syntax::Strftime,
+                "%Y%m%d_{0}",
+                Syntax::Reference,
+                "2003-02-01_%0",
+                "20210601_foo",
+                "2021-06-01_foo",
+            ),
+            (
+                Syntax::Reference,
+                "01.02.2003_%0",
+                Syntax::Strftime,
+                "%Y-%m-%d_{0}",
+                "01.06.2021_foo",
+                "2021-06-01_foo",
+            ),
+            (
+                Syntax::Strftime,
+                "%d.%m.%Y_v2_{0}",
+                Syntax::Strftime,
+                "%A_{0}_v2",
+                "01.06.2021_v2_foo",
+                "Tuesday_foo_v2",
+            ),
+        ];
+        for (source_syntax, s, target_syntax, t, name, want) in cases.iter() {
+            let mut source = FormatParser::new(s);
+            source.syntax = *source_syntax;
+            source.parse()?;
+            let mut target = FormatParser::new(t);
+            target.syntax = *target_syntax;
+            target.parse()?;
+            check_compatible(&source, &target)?;
+            assert_eq!(&process(&source, &target, name)?, want);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_compatible() -> Result<(), MyCustomError> {
+        let cases = [
+            ("20030201_%0", "%0_2003-02-01", None),
+            ("2003-W05", "sat_2003-032_Q1", None),
+            ("20030201", "1044122645", None),
+            ("2003-Q1", "2003-02", None),
+            ("%0_%1", "2003_%2_%1_%0", Some("year, fragment %2")),
+            ("feb_01", "sat_1044122645_18", Some("year, hour, weekday")),
+            ("1044122645", "%{client}", Some("fragment %{client}")),
+            ("Q1", "2003-W05", Some("year, week")),
+            ("%0[_2003]", "2003_%0", Some("year")),
+            ("%0_PM", "18_%0", Some("hour")),
+            ("%0_pm", "06pm_%0", Some("hour")),
+            ("%0_2003[-W05]", "2003_%0", Some("year")),
+            ("%0[_%1]", "%1_%0", None),
+        ];
+        for (s, t, want) in cases.iter() {
+            let mut source = FormatParser::new(s);
+            source.parse()?;
+            let mut target = FormatParser::new(t);
+            target.parse()?;
+            match (check_compatible(&source, &target), want) {
+                (Ok(()), None) => (),
+                (Err(MyCustomError::MissingIdentifiersError(got)), Some(want)) => {
+                    assert_eq!(got.join(", "), *want)
+                }
+                (got, _) => panic!("{} -> {}: unexpected {:?}", s, t, got),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_locales() -> Result<(), MyCustomError> {
+        let cases = [
+            (
+                Locale::De,
+                "1. März 2021",
+                Locale::En,
+                "February 1st 2003",
+                "March 1st 2021",
+            ),
+            (
+                Locale::De,
+                "1. MARZ 2021",
+                Locale::En,
+                "February 1st 2003",
+                "March 1st 2021",
+            ),
+            (
+                Locale::En,
+                "1. Aug 2021",
+                Locale::Fr,
+                "Saturday 1 february 2003",
+                "Dimanche 1 août 2021",
+            ),
+            (
+                Locale::Es,
+                "1. Sep 2021",
+                Locale::De,
+                "SAT 1. Feb 2003",
+                "MI 1. Sep 2021",
+            ),
+            (
+                Locale::Fr,
+                "1. février 2021",
+                Locale::It,
+                "saturday 1 FEBRUARY 2003",
+                "lunedì 1 FEBBRAIO 2021",
+            ),
+        ];
+        let mut source = FormatParser::new("1. February 2003");
+        source.parse()?;
+        for (source_locale, s, target_locale, t, want) in cases.iter() {
+            let mut target = FormatParser::new(t);
+            target.parse()?;
+            let opt = ParseOpt {
+                locale: *source_locale,
+                ..ParseOpt::default()
+            };
+            let d = parse(&source, s, &opt)?;
+            let render_opt = RenderOpt {
+                locale: *target_locale,
+                ..RenderOpt::default()
+            };
+            assert_eq!(render(&target.nodes, &d, 1, &render_opt), *want);
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,423 @@
+use crate::calendar;
+use crate::constants::{Months, Weekdays};
+use crate::error::MyCustomError;
+
+// Language of month and weekday names.
+#[derive(PartialEq, Copy, Clone, Debug, Default)]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+    Fr,
+    Es,
+    It,
+}
+
+impl std::str::FromStr for Locale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "en" => Ok(Locale::En),
+            "de" => Ok(Locale::De),
+            "fr" => Ok(Locale::Fr),
+            "es" => Ok(Locale::Es),
+            "it" => Ok(Locale::It),
+            _ => Err(format!(
+                "unknown locale {}, expected one of en, de, fr, es, it",
+                s
+            )),
+        }
+    }
+}
+
+impl Locale {
+    pub fn months(&self) -> [&'static str; 12] {
+        match self {
+            Locale::En => [
+                "january",
+                "february",
+                "march",
+                "april",
+                "may",
+                "june",
+                "july",
+                "august",
+                "september",
+                "october",
+                "november",
+                "december",
+            ],
+            Locale::De => [
+                "januar",
+                "februar",
+                "märz",
+                "april",
+                "mai",
+                "juni",
+                "juli",
+                "august",
+                "september",
+                "oktober",
+                "november",
+                "dezember",
+            ],
+            Locale::Fr => [
+                "janvier",
+                "février",
+                "mars",
+                "avril",
+                "mai",
+                "juin",
+                "juillet",
+                "août",
+                "septembre",
+                "octobre",
+                "novembre",
+                "décembre",
+            ],
+            Locale::Es => [
+                "enero",
+                "febrero",
+                "marzo",
+                "abril",
+                "mayo",
+                "junio",
+                "julio",
+                "agosto",
+                "septiembre",
+                "octubre",
+                "noviembre",
+                "diciembre",
+            ],
+            Locale::It => [
+                "gennaio",
+                "febbraio",
+                "marzo",
+                "aprile",
+                "maggio",
+                "giugno",
+                "luglio",
+                "agosto",
+                "settembre",
+                "ottobre",
+                "novembre",
+                "dicembre",
+            ],
+        }
+    }
+
+    pub fn month_abbreviations(&self) -> [&'static str; 12] {
+        match self {
+            Locale::En => [
+                "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+            ],
+            Locale::De => [
+                "jan", "feb", "mär", "apr", "mai", "jun", "jul", "aug", "sep", "okt", "nov", "dez",
+            ],
+            Locale::Fr => [
+                "janv", "févr", "mars", "avr", "mai", "juin", "juil", "août", "sept", "oct", "nov",
+                "déc",
+            ],
+            Locale::Es => [
+                "ene", "feb", "mar", "abr", "may", "jun", "jul", "ago", "sep", "oct", "nov", "dic",
+            ],
+            Locale::It => [
+                "gen", "feb", "mar", "apr", "mag", "giu", "lug", "ago", "set", "ott", "nov", "dic",
+            ],
+        }
+    }
+
+    // Weekdays starting with Monday.
+    pub fn weekdays(&self) -> [&'static str; 7] {
+        match self {
+            Locale::En => [
+                "monday",
+                "tuesday",
+                "wednesday",
+                "thursday",
+                "friday",
+                "saturday",
+                "sunday",
+            ],
+            Locale::De => [
+                "montag",
+                "dienstag",
+                "mittwoch",
+                "donnerstag",
+                "freitag",
+                "samstag",
+                "sonntag",
+            ],
+            Locale::Fr => [
+                "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche",
+            ],
+            Locale::Es => [
+                "lunes",
+                "martes",
+                "miércoles",
+                "jueves",
+                "viernes",
+                "sábado",
+                "domingo",
+            ],
+            Locale::It => [
+                "lunedì",
+                "martedì",
+                "mercoledì",
+                "giovedì",
+                "venerdì",
+                "sabato",
+                "domenica",
+            ],
+        }
+    }
+
+    pub fn weekday_abbreviations(&self) -> [&'static str; 7] {
+        match self {
+            Locale::En => ["mon", "tue", "wed", "thu", "fri", "sat", "sun"],
+            Locale::De => ["mo", "di", "mi", "do", "fr", "sa", "so"],
+            Locale::Fr => ["lun", "mar", "mer", "jeu", "ven", "sam", "dim"],
+            Locale::Es => ["lun", "mar", "mié", "jue", "vie", "sáb", "dom"],
+            Locale::It => ["lun", "mar", "mer", "gio", "ven", "sab", "dom"],
+        }
+    }
+
+    pub fn month(&self, m: Months) -> &'static str {
+        self.months()[m as usize]
+    }
+
+    pub fn month_abbreviation(&self, m: Months) -> &'static str {
+        self.month_abbreviations()[m as usize]
+    }
+
+    pub fn weekday(&self, w: Weekdays) -> &'static str {
+        self.weekdays()[w as usize]
+    }
+
+    pub fn weekday_abbreviation(&self, w: Weekdays) -> &'static str {
+        self.weekday_abbreviations()[w as usize]
+    }
+
+    // Further spellings of months, e.g. sept besides sep.
+    pub fn month_variants(&self) -> &'static [(&'static str, Months)] {
+        match self {
+            Locale::En => &[("sept", Months::Sep)],
+            Locale::De => &[
+                ("jän", Months::Jan),
+                ("jänner", Months::Jan),
+                ("feber", Months::Feb),
+                ("mrz", Months::Mar),
+                ("sept", Months::Sep),
+            ],
+            Locale::Fr => &[("fév", Months::Feb), ("sep", Months::Sep)],
+            Locale::Es => &[("sept", Months::Sep), ("set", Months::Sep)],
+            Locale::It => &[("sett", Months::Sep)],
+        }
+    }
+
+    // Returns the months whose names are a prefix of s together with the
+    // number of characters of the prefix, longest first. Abbreviations,
+    // variants and aliases are always accepted, full names only if long is set.
+    pub fn match_month(
+        &self,
+        s: &str,
+        long: bool,
+        aliases: &[(String, Months)],
+    ) -> Vec<(Months, usize)> {
+        let months = (1..=12).map(|i| calendar::month_from_number(i).unwrap());
+        let mut names: Vec<(&str, Months)> = self
+            .month_abbreviations()
+            .iter()
+            .copied()
+            .zip(months.clone())
+            .collect();
+        names.extend(self.month_variants().iter().copied());
+        names.extend(aliases.iter().map(|(a, m)| (&a[..], *m)));
+        if long {
+            names.extend(self.months().iter().copied().zip(months));
+        }
+        prefixes(&names, s)
+    }
+
+    // Returns the weekdays whose names are a prefix of s together with the
+    // number of characters of the prefix, longest first. Abbreviations are
+    // always accepted, full names only if long is set.
+    pub fn match_weekday(&self, s: &str, long: bool) -> Vec<(Weekdays, usize)> {
+        let weekdays = (0..7).map(calendar::weekday_from_number);
+        let mut names: Vec<(&str, Weekdays)> = self
+            .weekday_abbreviations()
+            .iter()
+            .copied()
+            .zip(weekdays.clone())
+            .collect();
+        if long {
+            names.extend(self.weekdays().iter().copied().zip(weekdays));
+        }
+        prefixes(&names, s)
+    }
+}
+
+// Returns the values of the names that are a prefix of s and the lengths of
+// the names in characters, longest first. Case and diacritics are ignored.
+fn prefixes<T: Copy + PartialEq>(names: &[(&str, T)], s: &str) -> Vec<(T, usize)> {
+    let s: Vec<char> = s.chars().map(fold).collect();
+    let mut res: Vec<(T, usize)> = Vec::new();
+    for (name, value) in names {
+        let name: Vec<char> = name.chars().map(fold).collect();
+        if s.starts_with(&name) && !res.contains(&(*value, name.len())) {
+            res.push((*value, name.len()));
+        }
+    }
+    res.sort_by_key(|x| std::cmp::Reverse(x.1));
+    res
+}
+
+// Reads month aliases from a file. Every line has the form "alias = month",
+// where month is a number from 1 to 12 or a name known to the locale. Empty
+// lines and lines starting with # are ignored.
+pub fn read_month_aliases(
+    path: &str,
+    locale: Locale,
+) -> Result<Vec<(String, Months)>, MyCustomError> {
+    let mut aliases = Vec::new();
+    for (i, line) in std::fs::read_to_string(path)?.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let err =
+            |reason: &str| MyCustomError::ParseError(format!("{}:{}: {}", path, i + 1, reason));
+        let (alias, month) = match line.split_once('=') {
+            Some((a, m)) => (a.trim(), m.trim()),
+            None => return Err(err("expected alias = month")),
+        };
+        if alias.is_empty() {
+            return Err(err("alias is empty"));
+        }
+        let month = match month.parse::<u32>() {
+            Ok(n) => calendar::month_from_number(n),
+            Err(_) => locale
+                .match_month(month, true, &[])
+                .into_iter()
+                .find(|(_, len)| *len == month.chars().count())
+                .map(|(m, _)| m),
+        };
+        match month {
+            Some(m) => aliases.push((alias.to_string(), m)),
+            None => return Err(err("unknown month")),
+        }
+    }
+    Ok(aliases)
+}
+
+// Lowercases c and removes its diacritics, e.g. Ä becomes a.
+pub fn fold(c: char) -> char {
+    match c.to_lowercase().next().unwrap_or(c) {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => 'a',
+        'ç' => 'c',
+        'è' | 'é' | 'ê' | 'ë' => 'e',
+        'ì' | 'í' | 'î' | 'ï' => 'i',
+        'ñ' => 'n',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' => 'o',
+        'ù' | 'ú' | 'û' | 'ü' => 'u',
+        'ý' | 'ÿ' => 'y',
+        x => x,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::constants::{Months, Weekdays};
+    use crate::locale::{read_month_aliases, Locale};
+
+    #[test]
+    fn test_match_month() {
+        assert_eq!(
+            Locale::En.match_month("june_1", true, &[]),
+            [(Months::Jun, 4), (Months::Jun, 3)]
+        );
+        assert_eq!(
+            Locale::En.match_month("june_1", false, &[]),
+            [(Months::Jun, 3)]
+        );
+        assert_eq!(
+            Locale::De.match_month("März", true, &[]),
+            [(Months::Mar, 4), (Months::Mar, 3)]
+        );
+        assert_eq!(
+            Locale::De.match_month("MARZ", true, &[]),
+            [(Months::Mar, 4), (Months::Mar, 3)]
+        );
+        assert_eq!(
+            Locale::Fr.match_month("février", true, &[]),
+            [(Months::Feb, 7), (Months::Feb, 4), (Months::Feb, 3)]
+        );
+        assert_eq!(
+            Locale::Fr.match_month("fevr.", false, &[]),
+            [(Months::Feb, 4), (Months::Feb, 3)]
+        );
+        assert_eq!(
+            Locale::En.match_month("Januar", true, &[]),
+            [(Months::Jan, 3)]
+        );
+        assert_eq!(Locale::En.match_month("foo", true, &[]), []);
+        assert_eq!(
+            Locale::En.match_month("Sept.", false, &[]),
+            [(Months::Sep, 4), (Months::Sep, 3)]
+        );
+        let aliases = [("Hornung".to_string(), Months::Feb)];
+        assert_eq!(
+            Locale::De.match_month("HORNUNG", false, &aliases),
+            [(Months::Feb, 7)]
+        );
+    }
+
+    #[test]
+    fn test_match_weekday() {
+        assert_eq!(
+            Locale::En.match_weekday("Mon_", false),
+            [(Weekdays::Mon, 3)]
+        );
+        assert_eq!(
+            Locale::De.match_weekday("Dienstag", true),
+            [(Weekdays::Tue, 8), (Weekdays::Tue, 2)]
+        );
+        assert_eq!(
+            Locale::Es.match_weekday("MIERCOLES", true),
+            [(Weekdays::Wed, 9), (Weekdays::Wed, 3)]
+        );
+        assert_eq!(
+            Locale::It.match_weekday("venerdi", true),
+            [(Weekdays::Fri, 7), (Weekdays::Fri, 3)]
+        );
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(Locale::De.month(Months::Mar), "märz");
+        assert_eq!(Locale::Fr.month_abbreviation(Months::Aug), "août");
+        assert_eq!(Locale::En.weekday(Weekdays::Sun), "sunday");
+        assert_eq!(Locale::De.weekday_abbreviation(Weekdays::Sat), "sa");
+    }
+
+    #[test]
+    fn test_read_month_aliases() {
+        let path = std::env::temp_dir().join("tidy_month_aliases.txt");
+        std::fs::write(&path, "# Austrian\njänner = 1\n\nHornung = februar\n").unwrap();
+        let aliases = read_month_aliases(path.to_str().unwrap(), Locale::De).unwrap();
+        assert_eq!(
+            aliases,
+            [
+                ("jänner".to_string(), Months::Jan),
+                ("Hornung".to_string(), Months::Feb)
+            ]
+        );
+
+        std::fs::write(&path, "lenz = 13\n").unwrap();
+        assert!(read_month_aliases(path.to_str().unwrap(), Locale::De).is_err());
+        std::fs::write(&path, "lenz\n").unwrap();
+        assert!(read_month_aliases(path.to_str().unwrap(), Locale::De).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
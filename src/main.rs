@@ -1,6 +1,7 @@
-use ansi_term::Colour::Red;
-use clap::{App, Arg};
+use ansi_term::Colour::{Green, Red};
+use clap::{App, AppSettings, Arg, ArgMatches};
 use log::debug;
+use std::path::PathBuf;
 use tidy::run;
 
 fn main() {
@@ -8,6 +9,7 @@ fn main() {
         .version("1.0")
         .author("stefan hengl")
         .about("Same date format for all filenames")
+        .setting(AppSettings::SubcommandsNegateReqs)
         .arg(
             Arg::new("source")
                 .about("Describes the format you want to change")
@@ -54,13 +56,139 @@ fn main() {
                 .short('r')
                 .long("review")
         )
+        .arg(
+            Arg::new("counter-start")
+                .about("First number rendered by # in the target")
+                .value_name("N")
+                .long("counter-start")
+                .takes_value(true)
+                .default_value("1")
+                .validator(|s| s.parse::<u32>()),
+        )
+        .arg(
+            Arg::new("counter-scope")
+                .about("Files sharing a sequence of numbers rendered by # in the target")
+                .value_name("SCOPE")
+                .long("counter-scope")
+                .takes_value(true)
+                .possible_values(&["global", "dir", "date"])
+                .default_value("global"),
+        )
+        .arg(
+            Arg::new("source-locale")
+                .about("Language of month and weekday names in the source")
+                .value_name("LOCALE")
+                .long("source-locale")
+                .takes_value(true)
+                .possible_values(&["en", "de", "fr", "es", "it"])
+                .default_value("en"),
+        )
+        .arg(
+            Arg::new("target-locale")
+                .about("Language of month and weekday names in the target")
+                .value_name("LOCALE")
+                .long("target-locale")
+                .takes_value(true)
+                .possible_values(&["en", "de", "fr", "es", "it"])
+                .default_value("en"),
+        )
+        .arg(
+            Arg::new("month-aliases")
+                .about("File with further names of months in the source, one \"alias = month\" per line")
+                .value_name("FILE")
+                .long("month-aliases")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("pivot-year")
+                .about("Reads two-digit years above N as 19xx instead of 20xx")
+                .value_name("N")
+                .long("pivot-year")
+                .takes_value(true)
+                .validator(|s| match s.parse::<u32>() {
+                    Ok(n) if n <= 99 => Ok(()),
+                    _ => Err(String::from("expected a number from 0 to 99")),
+                }),
+        )
+        .arg(
+            Arg::new("epoch-offset")
+                .about("Offset of the local time from UTC for epoch timestamps, e.g. +02:00")
+                .value_name("OFFSET")
+                .long("epoch-offset")
+                .takes_value(true)
+                .default_value("utc")
+                .validator(|s| s.parse::<tidy::UtcOffset>()),
+        )
+        .arg(
+            Arg::new("ignore-case")
+                .about("Matches literals of the source regardless of case")
+                .long("ignore-case")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::new("source-syntax")
+                .about("Syntax of the source pattern, the reference date or strftime directives")
+                .value_name("SYNTAX")
+                .long("source-syntax")
+                .takes_value(true)
+                .possible_values(&["reference", "strftime"])
+                .default_value("reference"),
+        )
+        .arg(
+            Arg::new("target-syntax")
+                .about("Syntax of the target pattern, the reference date or strftime directives")
+                .value_name("SYNTAX")
+                .long("target-syntax")
+                .takes_value(true)
+                .possible_values(&["reference", "strftime"])
+                .default_value("reference"),
+        )
+        .arg(
+            Arg::new("on-conflict")
+                .about("What to do if files would get the same name or a name which is taken")
+                .value_name("STRATEGY")
+                .long("on-conflict")
+                .takes_value(true)
+                .possible_values(&["abort", "skip", "suffix", "newer", "larger", "identical"])
+                .default_value("abort"),
+        )
+        .arg(
+            Arg::new("atomic")
+                .about("Renames all files or none. Renames the files back if a rename fails")
+                .long("atomic")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::new("journal-dir")
+                .about("Directory of the journals which record the renames of every run")
+                .value_name("DIR")
+                .long("journal-dir")
+                .env("TIDY_JOURNAL_DIR")
+                .takes_value(true)
+                .global(true),
+        )
+        .subcommand(
+            App::new("undo")
+                .about("Renames the files of the last run back")
+                .arg(
+                    Arg::new("journal")
+                        .about("Journal of the run to undo instead of the last one")
+                        .value_name("JOURNAL")
+                        .index(1),
+                ),
+        )
         .get_matches();
 
+    if let Some(undo_matches) = matches.subcommand_matches("undo") {
+        undo(undo_matches);
+        return;
+    }
+
     debug!(
         "source: {}; target: {}; dir {}; sim {}; ignore {};",
-        &matches.value_of("source").unwrap(),
-        &matches.value_of("target").unwrap(),
-        &matches.value_of("dir").unwrap_or("."),
+        matches.value_of("source").unwrap(),
+        matches.value_of("target").unwrap(),
+        matches.value_of("dir").unwrap_or("."),
         &matches.is_present("sim"),
         &matches.is_present("ignore")
     );
@@ -69,15 +197,72 @@ fn main() {
         simulate: matches.is_present("sim"),
         force: matches.is_present("ignore"),
         review: matches.is_present("review"),
+        counter_start: matches.value_of_t_or_exit("counter-start"),
+        counter_scope: matches.value_of_t_or_exit("counter-scope"),
+        source_locale: matches.value_of_t_or_exit("source-locale"),
+        target_locale: matches.value_of_t_or_exit("target-locale"),
+        month_aliases: matches.value_of("month-aliases").map(String::from),
+        pivot_year: matches
+            .value_of("pivot-year")
+            .map(|_| matches.value_of_t_or_exit("pivot-year")),
+        epoch_offset: matches.value_of_t_or_exit("epoch-offset"),
+        ignore_case: matches.is_present("ignore-case"),
+        source_syntax: matches.value_of_t_or_exit("source-syntax"),
+        target_syntax: matches.value_of_t_or_exit("target-syntax"),
+        on_conflict: matches.value_of_t_or_exit("on-conflict"),
+        journal_dir: journal_dir(&matches),
+        atomic: matches.is_present("atomic"),
     };
 
     match run(
-        &matches.value_of("source").unwrap(),
-        &matches.value_of("target").unwrap(),
-        &matches.value_of("dir").unwrap_or("."),
+        matches.value_of("source").unwrap(),
+        matches.value_of("target").unwrap(),
+        matches.value_of("dir").unwrap_or("."),
         opt,
     ) {
         Ok(_) => println!("OK"),
-        Err(e) => println!("{}\n", Red.paint(format!("ERR: {}", e.to_string()))),
+        Err(e) => println!("{}\n", Red.paint(format!("ERR: {}", e))),
+    }
+}
+
+fn journal_dir(matches: &ArgMatches) -> Option<PathBuf> {
+    matches
+        .value_of("journal-dir")
+        .map(PathBuf::from)
+        .or_else(tidy::default_journal_dir)
+}
+
+fn undo(matches: &ArgMatches) {
+    let path = match (matches.value_of("journal"), journal_dir(matches)) {
+        (Some(p), _) => Ok(PathBuf::from(p)),
+        (None, Some(dir)) => tidy::last_journal(&dir),
+        (None, None) => Err("no journal directory, use --journal-dir".into()),
+    };
+    match path.and_then(|p| tidy::undo(&p).map(|report| (p, report))) {
+        Ok((p, report)) => {
+            for e in &report.reverted {
+                print!(
+                    "{}\n{}\n",
+                    Red.paint(format!("-{}", e.to.display())),
+                    Green.paint(format!("+{}", e.from.display()))
+                );
+            }
+            for e in &report.failed {
+                println!("{}", Red.paint(format!("ERR: {}", e.error)));
+            }
+            if report.failed.is_empty() {
+                println!("OK");
+            } else {
+                println!(
+                    "{}\n",
+                    Red.paint(format!(
+                        "ERR: {} files could not be renamed back, they are kept in {}",
+                        report.failed.len(),
+                        p.display()
+                    ))
+                );
+            }
+        }
+        Err(e) => println!("{}\n", Red.paint(format!("ERR: {}", e))),
     }
 }
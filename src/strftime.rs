@@ -0,0 +1,201 @@
+use crate::error::MyCustomError;
+use crate::format_parser::{FormatParser, Node};
+use log::debug;
+
+// The dialect of a pattern. The reference syntax writes the reference date,
+// e.g. 2003-02-01, while the strftime syntax uses directives such as %Y-%m-%d
+// and writes fragments in braces, e.g. {0} or {client}. Both compile to the
+// same nodes.
+#[derive(PartialEq, Copy, Clone, Debug, Default)]
+pub enum Syntax {
+    #[default]
+    Reference,
+    Strftime,
+}
+
+impl std::str::FromStr for Syntax {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reference" => Ok(Syntax::Reference),
+            "strftime" => Ok(Syntax::Strftime),
+            _ => Err(format!(
+                "unknown syntax {}, expected reference or strftime",
+                s
+            )),
+        }
+    }
+}
+
+const DIRECTIVES: &str =
+    "%Y, %y, %G, %m, %-m, %d, %-d, %j, %b, %B, %a, %A, %H, %I, %M, %S, %p, %P, %s, W%V, Q%q or %%";
+
+// Returns the node of a directive, given the character after the % and the
+// flag before it, if any.
+fn directive(flag: Option<char>, c: char) -> Option<Node> {
+    let node = match (flag, c) {
+        (None, 'Y') => Node::Year,
+        (None, 'y') => Node::YearShort,
+        (None, 'G') => Node::IsoYear,
+        (None, 'm') => Node::FebNum,
+        (Some('-'), 'm') => Node::FebNumUnpadded,
+        (None, 'd') => Node::Day,
+        (Some('-'), 'd') => Node::DayUnpadded,
+        (None, 'j') => Node::DayOfYear,
+        (None, 'b') | (None, 'h') => Node::FebCaps,
+        (Some('^'), 'b') | (Some('^'), 'h') => Node::FebAllCaps,
+        (None, 'B') => Node::FebruaryCaps,
+        (Some('^'), 'B') => Node::FebruaryAllCaps,
+        (None, 'a') => Node::SatCaps,
+        (Some('^'), 'a') => Node::SatAllCaps,
+        (None, 'A') => Node::SaturdayCaps,
+        (Some('^'), 'A') => Node::SaturdayAllCaps,
+        (None, 'H') => Node::Hour,
+        (None, 'I') => Node::Hour12,
+        (None, 'M') => Node::Minute,
+        (None, 'S') => Node::Second,
+        (None, 'p') => Node::PmAllCaps,
+        (None, 'P') => Node::Pm,
+        (None, 's') => Node::EpochSeconds,
+        _ => return None,
+    };
+    Some(node)
+}
+
+impl FormatParser<'_> {
+    // Parses a directive, a fragment in braces or a literal written in the
+    // strftime syntax. The week and the quarter are rendered with their
+    // prefix, so they are written as W%V and Q%q.
+    pub(crate) fn parse_directive(&mut self) -> Result<(), MyCustomError> {
+        debug!("parse_directive");
+        match self.input.peek().copied() {
+            Some('{') => return self.parse_fragment(),
+            Some('W') if self.lookahead("W%V") => {
+                self.input.nth(2);
+                self.nodes.push(Node::IsoWeek);
+                return Ok(());
+            }
+            Some('Q') if self.lookahead("Q%q") => {
+                self.input.nth(2);
+                self.nodes.push(Node::Quarter);
+                return Ok(());
+            }
+            Some('%') => (),
+            _ => return self.parse_literal(),
+        }
+        self.input.next();
+        let flag = match self.input.peek().copied() {
+            Some(x) if x == '-' || x == '^' => {
+                self.input.next();
+                Some(x)
+            }
+            _ => None,
+        };
+        let c = match self.input.next() {
+            Some(c) => c,
+            None => {
+                return Err(self.error(format!(
+                    "pattern must not end with {}, expected {}",
+                    flag.map_or("%".to_string(), |f| format!("%{}", f)),
+                    DIRECTIVES
+                )))
+            }
+        };
+        match (flag, c) {
+            (None, 'V') => Err(self.error("the week is written as W%V".to_string())),
+            (None, 'q') => Err(self.error("the quarter is written as Q%q".to_string())),
+            (None, '%') => {
+                self.push_literal("%");
+                Ok(())
+            }
+            _ => match directive(flag, c) {
+                Some(node) => {
+                    if matches!(node, Node::IsoYear) {
+                        let span = self.span();
+                        self.iso_year.get_or_insert(span);
+                    }
+                    self.nodes.push(node);
+                    Ok(())
+                }
+                None => Err(self.error(format!(
+                    "unknown directive %{}{}, expected {}",
+                    flag.map_or(String::new(), String::from),
+                    c,
+                    DIRECTIVES
+                ))),
+            },
+        }
+    }
+
+    // Returns true if the next character starts a directive, a fragment or a
+    // group.
+    pub(crate) fn at_directive(&self) -> bool {
+        match self.input.clone().next() {
+            Some(x) if "%{#[]()|".contains(x) => true,
+            Some(_) => self.lookahead("W%V") || self.lookahead("Q%q"),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::MyCustomError;
+    use crate::format_parser::FormatParser;
+    use crate::strftime::Syntax;
+
+    fn parse(s: &str, syntax: Syntax) -> Result<FormatParser<'_>, MyCustomError> {
+        let mut p = FormatParser::new(s);
+        p.syntax = syntax;
+        p.parse()?;
+        Ok(p)
+    }
+
+    #[test]
+    fn test_same_nodes() -> Result<(), MyCustomError> {
+        for (strftime, reference) in &[
+            ("%Y-%m-%d_{0}", "2003-02-01_%0"),
+            ("%y%m%d_%H%M%S", "030201_180405"),
+            ("%-d.%-m.%Y", "1.2.2003"),
+            ("%b_%B_%^b_%^B", "Feb_February_FEB_FEBRUARY"),
+            ("%a_%A_%^a_%^A", "Sat_Saturday_SAT_SATURDAY"),
+            ("%I.%M%p_%I%P", "06.04PM_06pm"),
+            ("%G-W%V", "2003-W05"),
+            ("%Y-Q%q_%j", "2003-Q1_032"),
+            ("%s_{client}", "1044122645_%{client}"),
+            ("{0}:d_{1}|upper", "%0:d_%1|upper"),
+            ("%Y[_{0}]", "2003[_%0]"),
+            ("(%d.%m.%Y|%Y-%m-%d)_###", "(01.02.2003|2003-02-01)_###"),
+            ("final_v2_100%%", r"\final_v\2_\1\0\0\%"),
+            (r"\{0\}_%d", r"{\0}_01"),
+        ] {
+            let got = parse(strftime, Syntax::Strftime)?;
+            let want = parse(reference, Syntax::Reference)?;
+            assert_eq!(got.nodes, want.nodes, "{}", strftime);
+            assert_eq!(got.holes, want.holes, "{}", strftime);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid() {
+        for (s, start, end, message) in &[
+            ("%Y-%x", 3, 5, "unknown directive %x"),
+            ("%Y-%-Y", 3, 6, "unknown directive %-Y"),
+            ("%Y-%V", 3, 5, "the week is written as W%V"),
+            ("%Y-%q", 3, 5, "the quarter is written as Q%q"),
+            ("%Y%", 2, 3, "pattern must not end with %"),
+            ("{0", 0, 2, "could not parse hole, missing }"),
+            ("%G_{0}", 0, 2, "the week-year %G requires the week W%V"),
+        ] {
+            match parse(s, Syntax::Strftime) {
+                Err(MyCustomError::PatternError(_, got_start, got_end, got)) => {
+                    assert_eq!((got_start, got_end), (*start, *end), "{}", s);
+                    assert!(got.starts_with(message), "{}: {}", s, got);
+                }
+                _ => panic!("{} should not parse", s),
+            }
+        }
+    }
+}